@@ -0,0 +1,796 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::device::{parse_device_list, Device};
+
+pub const DEFAULT_ADB_HOST: &str = "127.0.0.1";
+pub const DEFAULT_ADB_PORT: u16 = 5037;
+
+const SYNC_CHUNK_SIZE: usize = 64 * 1024;
+const DEFAULT_FILE_MODE: u32 = 0o100644;
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+#[derive(Debug)]
+pub enum AdbError {
+    /// The adb server couldn't be reached at all, callers may fall back to the CLI.
+    Unavailable(String),
+    /// The server or the device rejected or broke off the request.
+    Failed(String),
+}
+
+impl fmt::Display for AdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdbError::Unavailable(msg) | AdbError::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for AdbError {
+    fn from(e: std::io::Error) -> Self {
+        AdbError::Failed(e.to_string())
+    }
+}
+
+/// Sends a hex-length-prefixed host request and waits for the server's OKAY/FAIL reply.
+pub async fn send_request<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, request: &str) -> Result<(), AdbError> {
+    let framed = format!("{:04x}{}", request.len(), request);
+    stream.write_all(framed.as_bytes()).await?;
+    read_status(stream).await
+}
+
+async fn read_status<S: AsyncRead + Unpin>(stream: &mut S) -> Result<(), AdbError> {
+    let mut status = [0u8; 4];
+    stream.read_exact(&mut status).await?;
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => {
+            let message = read_hex_payload(stream).await.unwrap_or_default();
+            Err(AdbError::Failed(message))
+        }
+        other => Err(AdbError::Failed(format!("Unexpected adb server reply: {}", String::from_utf8_lossy(other)))),
+    }
+}
+
+/// Reads one `<4 hex digit length><payload>` message.
+pub async fn read_hex_payload<S: AsyncRead + Unpin>(stream: &mut S) -> Result<String, AdbError> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let len_str = String::from_utf8_lossy(&len_buf).to_string();
+    let len = usize::from_str_radix(&len_str, 16)
+        .map_err(|_| AdbError::Failed(format!("Invalid length prefix: {}", len_str)))?;
+
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok(String::from_utf8_lossy(&payload).to_string())
+}
+
+async fn write_sync_header<S: AsyncWrite + Unpin>(stream: &mut S, id: &[u8; 4], len: u32) -> Result<(), AdbError> {
+    stream.write_all(id).await?;
+    stream.write_all(&len.to_le_bytes()).await?;
+    Ok(())
+}
+
+async fn read_sync_header<S: AsyncRead + Unpin>(stream: &mut S) -> Result<([u8; 4], u32), AdbError> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await?;
+    let id = [header[0], header[1], header[2], header[3]];
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    Ok((id, len))
+}
+
+async fn read_u32<S: AsyncRead + Unpin>(stream: &mut S) -> Result<u32, AdbError> {
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf).await?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Reads the rest of a v2 stat or directory entry after its id and error
+/// fields: dev, ino, mode, nlink, uid, gid, size, atime, mtime, ctime.
+async fn read_stat_v2<S: AsyncRead + Unpin>(stream: &mut S) -> Result<RemoteStat, AdbError> {
+    let mut buf = [0u8; 64];
+    stream.read_exact(&mut buf).await?;
+    let u64_at = |offset: usize| u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap());
+    Ok(RemoteStat {
+        mode: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
+        size: u64_at(32),
+        // mtime is signed, pre-1970 times aren't worth keeping
+        mtime: (u64_at(48) as i64).max(0) as u64,
+    })
+}
+
+/// Builds the error for a sync reply that isn't the expected one, reading the
+/// message that follows a FAIL.
+async fn sync_error<S: AsyncRead + Unpin>(stream: &mut S, id: [u8; 4], len: u32) -> AdbError {
+    if &id == b"FAIL" {
+        let mut msg = vec![0u8; len as usize];
+        if let Err(e) = stream.read_exact(&mut msg).await {
+            return e.into();
+        }
+        AdbError::Failed(String::from_utf8_lossy(&msg).to_string())
+    } else {
+        AdbError::Failed(format!("Unexpected sync reply: {}", String::from_utf8_lossy(&id)))
+    }
+}
+
+async fn write_sync_request<S: AsyncWrite + Unpin>(stream: &mut S, id: &[u8; 4], path: &str) -> Result<(), AdbError> {
+    write_sync_header(stream, id, path.len() as u32).await?;
+    stream.write_all(path.as_bytes()).await?;
+    Ok(())
+}
+
+/// File metadata as reported by the sync service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoteStat {
+    pub mode: u32,
+    pub size: u64,
+    pub mtime: u64,
+}
+
+impl RemoteStat {
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+}
+
+/// One entry of a remote directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDirEntry {
+    pub name: String,
+    pub stat: RemoteStat,
+}
+
+/// Printed after a shell command, followed by its exit status. The command runs
+/// in a subshell so an `exit` in it can't skip the marker.
+const EXIT_MARKER: &str = "__scrcpy_gui_exit:";
+
+/// Output and exit status of a shell command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellOutput {
+    pub output: String,
+    /// `None` when the command didn't report one, e.g. because it was killed.
+    pub exit_code: Option<i32>,
+}
+
+impl ShellOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// The output on success, otherwise an error built from it.
+    pub fn into_result(self) -> Result<String, String> {
+        if self.success() {
+            return Ok(self.output);
+        }
+        match (self.output.trim(), self.exit_code) {
+            ("", Some(code)) => Err(format!("Command exited with code {}", code)),
+            ("", None) => Err("Command ended without an exit status".to_string()),
+            (output, _) => Err(output.to_string()),
+        }
+    }
+}
+
+fn shell_with_exit_status(command: &str) -> String {
+    format!("(\n{}\n)\necho; echo {}$?", command, EXIT_MARKER)
+}
+
+/// Splits the exit marker off the output of a `shell_with_exit_status` command.
+fn parse_shell_output(raw: &str) -> ShellOutput {
+    let Some(index) = raw.rfind(EXIT_MARKER) else {
+        return ShellOutput { output: raw.to_string(), exit_code: None };
+    };
+    let exit_code = raw[index + EXIT_MARKER.len()..].trim().parse().ok();
+    // Drop the line break echoed before the marker, a pty turns it into \r\n
+    let output = &raw[..index];
+    let output = output.strip_suffix('\n').unwrap_or(output);
+    let output = output.strip_suffix('\r').unwrap_or(output);
+    ShellOutput { output: output.to_string(), exit_code }
+}
+
+/// Joins a remote directory and a name with exactly one `/`.
+pub fn remote_join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+/// The adb server every adb call and scrcpy session goes through. Lets several
+/// isolated servers coexist, or a remote one be used through an SSH tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AdbServer {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for AdbServer {
+    fn default() -> Self {
+        Self { host: DEFAULT_ADB_HOST.to_string(), port: DEFAULT_ADB_PORT }
+    }
+}
+
+impl AdbServer {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.trim().is_empty() {
+            return Err("adb server host can't be empty".to_string());
+        }
+        if self.port == 0 {
+            return Err("adb server port can't be 0".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Value for `ADB_SERVER_SOCKET`, which both adb and scrcpy (through the adb it runs) honor.
+    pub fn socket_spec(&self) -> String {
+        format!("tcp:{}:{}", self.host, self.port)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let server: Self = toml::from_str(&text).map_err(|e| format!("Invalid adb server settings: {}", e))?;
+        server.validate()?;
+        Ok(server)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = toml::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("Failed to save adb server settings: {}", e))
+    }
+}
+
+static SERVER: RwLock<Option<AdbServer>> = RwLock::new(None);
+
+/// The configured adb server, the local default unless changed with `set_server`.
+pub fn server() -> AdbServer {
+    SERVER.read().unwrap().clone().unwrap_or_default()
+}
+
+pub fn set_server(server: AdbServer) {
+    *SERVER.write().unwrap() = Some(server);
+}
+
+/// Client for the adb server's host protocol, talking to it directly over TCP
+/// instead of spawning the `adb` binary for every call.
+#[derive(Debug, Clone)]
+pub struct AdbClient {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for AdbClient {
+    /// Talks to the configured adb server.
+    fn default() -> Self {
+        let server = server();
+        Self::new(server.host, server.port)
+    }
+}
+
+impl AdbClient {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self { host: host.into(), port }
+    }
+
+    async fn connect(&self) -> Result<TcpStream, AdbError> {
+        TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| AdbError::Unavailable(format!("Failed to reach adb server at {}:{}: {}", self.host, self.port, e)))
+    }
+
+    /// Runs a `host:` request whose reply is a single length-prefixed payload.
+    pub async fn host_request(&self, request: &str) -> Result<String, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, request).await?;
+        read_hex_payload(&mut stream).await
+    }
+
+    /// Opens a connection already switched to the given device's transport.
+    async fn transport(&self, serial: &str) -> Result<TcpStream, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, &format!("host:transport:{}", serial)).await?;
+        Ok(stream)
+    }
+
+    pub async fn devices(&self) -> Result<Vec<Device>, AdbError> {
+        let payload = self.host_request("host:devices-l").await?;
+        Ok(parse_device_list(&payload))
+    }
+
+    /// Returns a stream on which the server pushes a fresh device list on every change.
+    pub async fn track_devices(&self) -> Result<TcpStream, AdbError> {
+        let mut stream = self.connect().await?;
+        send_request(&mut stream, "host:track-devices-l").await?;
+        Ok(stream)
+    }
+
+    /// Equivalent of `adb connect <address>`, returns the server's message.
+    pub async fn connect_device(&self, address: &str) -> Result<String, AdbError> {
+        self.host_request(&format!("host:connect:{}", address)).await
+    }
+
+    /// Runs a shell command and returns its combined output and exit status.
+    pub async fn shell(&self, serial: &str, command: &str) -> Result<ShellOutput, AdbError> {
+        let mut stream = self.transport(serial).await?;
+        send_request(&mut stream, &format!("shell:{}", shell_with_exit_status(command))).await?;
+
+        let mut output = Vec::new();
+        stream.read_to_end(&mut output).await?;
+        Ok(parse_shell_output(&String::from_utf8_lossy(&output)))
+    }
+
+    async fn sync_session(&self, serial: &str) -> Result<TcpStream, AdbError> {
+        let mut stream = self.transport(serial).await?;
+        send_request(&mut stream, "sync:").await?;
+        Ok(stream)
+    }
+
+    /// The device's adb features, e.g. `stat_v2` and `ls_v2`.
+    pub async fn features(&self, serial: &str) -> Result<Vec<String>, AdbError> {
+        let payload = self.host_request(&format!("host-serial:{}:features", serial)).await?;
+        Ok(payload.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+    }
+
+    async fn has_feature(&self, serial: &str, feature: &str) -> Result<bool, AdbError> {
+        Ok(self.features(serial).await?.iter().any(|f| f == feature))
+    }
+
+    /// Stats a path without following a final symlink. Uses the 64-bit `LST2`
+    /// when the device supports it, a missing path has a mode of 0.
+    pub async fn stat(&self, serial: &str, remote_path: &str) -> Result<RemoteStat, AdbError> {
+        let v2 = self.has_feature(serial, "stat_v2").await?;
+        let mut stream = self.sync_session(serial).await?;
+        write_sync_request(&mut stream, if v2 { b"LST2" } else { b"STAT" }, remote_path).await?;
+
+        let (id, value) = read_sync_header(&mut stream).await?;
+        let stat = match &id {
+            b"LST2" if v2 => {
+                let stat = read_stat_v2(&mut stream).await?;
+                // value is the errno, the other fields are zeroed along with the mode
+                if value == 0 { stat } else { RemoteStat { mode: 0, size: 0, mtime: 0 } }
+            }
+            b"STAT" if !v2 => RemoteStat { mode: value, size: read_u32(&mut stream).await? as u64, mtime: read_u32(&mut stream).await? as u64 },
+            _ => return Err(sync_error(&mut stream, id, value).await),
+        };
+        let _ = write_sync_header(&mut stream, b"QUIT", 0).await;
+        Ok(stat)
+    }
+
+    /// Lists a remote directory, without the `.` and `..` entries. Uses the
+    /// 64-bit `LIS2` when the device supports it, sizes over 4 GiB are wrong otherwise.
+    pub async fn list_dir(&self, serial: &str, remote_path: &str) -> Result<Vec<RemoteDirEntry>, AdbError> {
+        let v2 = self.has_feature(serial, "ls_v2").await?;
+        let mut stream = self.sync_session(serial).await?;
+        write_sync_request(&mut stream, if v2 { b"LIS2" } else { b"LIST" }, remote_path).await?;
+
+        let entry_id = if v2 { b"DNT2" } else { b"DENT" };
+        let mut entries = Vec::new();
+        loop {
+            let (id, value) = read_sync_header(&mut stream).await?;
+            if &id != entry_id && &id != b"DONE" {
+                return Err(sync_error(&mut stream, id, value).await);
+            }
+            let stat = if v2 {
+                read_stat_v2(&mut stream).await?
+            } else {
+                RemoteStat { mode: value, size: read_u32(&mut stream).await? as u64, mtime: read_u32(&mut stream).await? as u64 }
+            };
+            let name_len = read_u32(&mut stream).await?;
+            if &id == b"DONE" {
+                break;
+            }
+
+            let mut name = vec![0u8; name_len as usize];
+            stream.read_exact(&mut name).await?;
+            let name = String::from_utf8_lossy(&name).to_string();
+            if name != "." && name != ".." {
+                entries.push(RemoteDirEntry { name, stat });
+            }
+        }
+
+        let _ = write_sync_header(&mut stream, b"QUIT", 0).await;
+        Ok(entries)
+    }
+
+    /// Copies a remote file to `local_path`, returning the number of bytes received.
+    /// A partially written file is removed on failure.
+    /// `progress` is called with the size of every chunk received.
+    pub async fn pull(
+        &self,
+        serial: &str,
+        remote_path: &str,
+        local_path: &Path,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<u64, AdbError> {
+        let mut stream = self.sync_session(serial).await?;
+        write_sync_request(&mut stream, b"RECV", remote_path).await?;
+
+        let mut file = tokio::fs::File::create(local_path).await?;
+        let mut buf = vec![0u8; SYNC_CHUNK_SIZE];
+        let mut received: u64 = 0;
+
+        let result = loop {
+            let (id, len) = match read_sync_header(&mut stream).await {
+                Ok(header) => header,
+                Err(e) => break Err(e),
+            };
+            match &id {
+                b"DATA" if len as usize <= SYNC_CHUNK_SIZE => {
+                    let chunk = &mut buf[..len as usize];
+                    if let Err(e) = stream.read_exact(chunk).await {
+                        break Err(e.into());
+                    }
+                    if let Err(e) = file.write_all(chunk).await {
+                        break Err(e.into());
+                    }
+                    received += len as u64;
+                    progress(len as u64);
+                }
+                b"DONE" => break file.flush().await.map(|_| received).map_err(AdbError::from),
+                _ => break Err(sync_error(&mut stream, id, len).await),
+            }
+        };
+
+        if result.is_err() {
+            drop(file);
+            let _ = tokio::fs::remove_file(local_path).await;
+        } else {
+            let _ = write_sync_header(&mut stream, b"QUIT", 0).await;
+        }
+        result
+    }
+
+    /// Pushes a local file to `remote_path` (a full file path, not a directory) via the
+    /// sync service. Returns the number of bytes sent.
+    /// `progress` is called with the size of every chunk sent.
+    pub async fn push(
+        &self,
+        serial: &str,
+        local_path: &Path,
+        remote_path: &str,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<u64, AdbError> {
+        let mut file = tokio::fs::File::open(local_path).await?;
+        let mtime = file.metadata().await?
+            .modified().ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+
+        let mut stream = self.sync_session(serial).await?;
+        write_sync_request(&mut stream, b"SEND", &format!("{},{}", remote_path, DEFAULT_FILE_MODE)).await?;
+
+        let mut buf = vec![0u8; SYNC_CHUNK_SIZE];
+        let mut sent: u64 = 0;
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 { break; }
+            write_sync_header(&mut stream, b"DATA", n as u32).await?;
+            stream.write_all(&buf[..n]).await?;
+            sent += n as u64;
+            progress(n as u64);
+        }
+        write_sync_header(&mut stream, b"DONE", mtime).await?;
+
+        let (id, len) = read_sync_header(&mut stream).await?;
+        let result = match &id {
+            b"OKAY" => Ok(sent),
+            _ => Err(sync_error(&mut stream, id, len).await),
+        };
+
+        let _ = write_sync_header(&mut stream, b"QUIT", 0).await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn read_request(stream: &mut TcpStream) -> String {
+        read_hex_payload(stream).await.unwrap()
+    }
+
+    async fn reply_okay(stream: &mut TcpStream, payload: Option<&str>) {
+        stream.write_all(b"OKAY").await.unwrap();
+        if let Some(p) = payload {
+            stream.write_all(format!("{:04x}{}", p.len(), p).as_bytes()).await.unwrap();
+        }
+    }
+
+    /// Minimal stand-in for the adb server handling one connection per request.
+    async fn fake_server() -> (AdbClient, tokio::task::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = tokio::spawn(async move {
+            let mut pushed = Vec::new();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut stream).await;
+                match request.as_str() {
+                    "host:devices-l" => {
+                        reply_okay(&mut stream, Some("emulator-5554          device product:sdk model:Pixel_7 device:emu transport_id:1\n")).await;
+                    }
+                    "host:transport:emulator-5554" => {
+                        reply_okay(&mut stream, None).await;
+                        let service = read_request(&mut stream).await;
+                        if service == format!("shell:{}", shell_with_exit_status("echo hello")) {
+                            reply_okay(&mut stream, None).await;
+                            stream.write_all(format!("hello\n\n{}0\n", EXIT_MARKER).as_bytes()).await.unwrap();
+                        } else if service == format!("shell:{}", shell_with_exit_status("ls /missing")) {
+                            reply_okay(&mut stream, None).await;
+                            stream.write_all(format!("ls: /missing: No such file or directory\n\n{}1\n", EXIT_MARKER).as_bytes()).await.unwrap();
+                        } else if service == "sync:" {
+                            reply_okay(&mut stream, None).await;
+                            let (id, len) = read_sync_header(&mut stream).await.unwrap();
+                            assert_eq!(&id, b"SEND");
+                            let mut target = vec![0u8; len as usize];
+                            stream.read_exact(&mut target).await.unwrap();
+                            assert!(String::from_utf8_lossy(&target).starts_with("/sdcard/Download/test.txt,"));
+                            loop {
+                                let (id, len) = read_sync_header(&mut stream).await.unwrap();
+                                if &id == b"DONE" { break; }
+                                let mut chunk = vec![0u8; len as usize];
+                                stream.read_exact(&mut chunk).await.unwrap();
+                                pushed.extend_from_slice(&chunk);
+                            }
+                            write_sync_header(&mut stream, b"OKAY", 0).await.unwrap();
+                            return pushed;
+                        }
+                    }
+                    _ => {
+                        let msg = "device 'missing' not found";
+                        stream.write_all(format!("FAIL{:04x}{}", msg.len(), msg).as_bytes()).await.unwrap();
+                    }
+                }
+            }
+        });
+
+        (AdbClient::new("127.0.0.1", port), handle)
+    }
+
+    #[tokio::test]
+    async fn test_client_against_fake_server() {
+        let (client, server) = fake_server().await;
+
+        let devices = client.devices().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].model.as_deref(), Some("Pixel_7"));
+
+        let hello = client.shell("emulator-5554", "echo hello").await.unwrap();
+        assert_eq!(hello, ShellOutput { output: "hello\n".to_string(), exit_code: Some(0) });
+
+        let failed = client.shell("emulator-5554", "ls /missing").await.unwrap();
+        assert_eq!(failed.exit_code, Some(1));
+        assert!(!failed.success());
+        assert_eq!(failed.into_result(), Err("ls: /missing: No such file or directory".to_string()));
+
+        let err = client.shell("missing", "true").await.unwrap_err();
+        assert!(matches!(err, AdbError::Failed(ref m) if m.contains("not found")));
+
+        let local = std::env::temp_dir().join("scrcpy_gui_adb_push_test.txt");
+        std::fs::write(&local, b"payload").unwrap();
+        let sent = client.push("emulator-5554", &local, "/sdcard/Download/test.txt", &mut |_| {}).await.unwrap();
+        let _ = std::fs::remove_file(&local);
+        assert_eq!(sent, 7);
+        assert_eq!(server.await.unwrap(), b"payload");
+    }
+
+    /// Serves the sync service over a single transport connection from an in-memory file tree.
+    /// `large` files only exist for stat and listings, with their given size.
+    /// With `v2` the device reports `stat_v2` and `ls_v2`.
+    async fn fake_sync_server(files: Vec<(&'static str, &'static [u8])>, large: Vec<(&'static str, u64)>, v2: bool) -> AdbClient {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let sizes: Vec<(&str, u64)> = files.iter().map(|(p, d)| (*p, d.len() as u64)).chain(large.iter().copied()).collect();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                if read_request(&mut stream).await.ends_with(":features") {
+                    reply_okay(&mut stream, Some(if v2 { "shell_v2,cmd,stat_v2,ls_v2" } else { "shell_v2,cmd" })).await;
+                    continue;
+                }
+                reply_okay(&mut stream, None).await; // host:transport:<serial>
+                read_request(&mut stream).await; // sync:
+                reply_okay(&mut stream, None).await;
+
+                let (id, len) = read_sync_header(&mut stream).await.unwrap();
+                let mut path = vec![0u8; len as usize];
+                stream.read_exact(&mut path).await.unwrap();
+                let path = String::from_utf8(path).unwrap();
+                let file = files.iter().find(|(p, _)| *p == path);
+                let size = sizes.iter().find(|(p, _)| *p == path).map(|(_, size)| *size);
+                let is_dir = sizes.iter().any(|(p, _)| p.starts_with(&format!("{}/", path)));
+
+                match &id {
+                    b"STAT" | b"LST2" => {
+                        let (mode, size) = match (size, is_dir) {
+                            (Some(size), _) => (S_IFREG | 0o644, size),
+                            (None, true) => (S_IFDIR | 0o755, 0),
+                            (None, false) => (0, 0),
+                        };
+                        if &id == b"STAT" {
+                            write_sync_header(&mut stream, b"STAT", mode).await.unwrap();
+                            stream.write_all(&(size as u32).to_le_bytes()).await.unwrap();
+                            stream.write_all(&0u32.to_le_bytes()).await.unwrap();
+                        } else {
+                            let errno = if mode == 0 { 2 } else { 0 };
+                            write_sync_header(&mut stream, b"LST2", errno).await.unwrap();
+                            stream.write_all(&stat_v2(mode, size)).await.unwrap();
+                        }
+                    }
+                    b"LIST" | b"LIS2" => {
+                        let prefix = format!("{}/", path);
+                        let mut seen = Vec::new();
+                        for (p, file_size) in sizes.iter().filter(|(p, _)| p.starts_with(&prefix)) {
+                            let rest = &p[prefix.len()..];
+                            let (name, mode, size) = match rest.split_once('/') {
+                                Some((dir, _)) => (dir, S_IFDIR | 0o755, 0),
+                                None => (rest, S_IFREG | 0o644, *file_size),
+                            };
+                            if seen.contains(&name) { continue; }
+                            seen.push(name);
+                            if &id == b"LIST" {
+                                write_sync_header(&mut stream, b"DENT", mode).await.unwrap();
+                                for v in [size as u32, 0, name.len() as u32] {
+                                    stream.write_all(&v.to_le_bytes()).await.unwrap();
+                                }
+                            } else {
+                                write_sync_header(&mut stream, b"DNT2", 0).await.unwrap();
+                                stream.write_all(&stat_v2(mode, size)).await.unwrap();
+                                stream.write_all(&(name.len() as u32).to_le_bytes()).await.unwrap();
+                            }
+                            stream.write_all(name.as_bytes()).await.unwrap();
+                        }
+                        stream.write_all(b"DONE").await.unwrap();
+                        stream.write_all(&vec![0u8; if &id == b"LIST" { 16 } else { 72 }]).await.unwrap();
+                    }
+                    b"RECV" if file.is_some() => {
+                        let data = file.unwrap().1;
+                        write_sync_header(&mut stream, b"DATA", data.len() as u32).await.unwrap();
+                        stream.write_all(data).await.unwrap();
+                        write_sync_header(&mut stream, b"DONE", 0).await.unwrap();
+                    }
+                    _ => {
+                        let msg = "No such file or directory";
+                        write_sync_header(&mut stream, b"FAIL", msg.len() as u32).await.unwrap();
+                        stream.write_all(msg.as_bytes()).await.unwrap();
+                    }
+                }
+            }
+        });
+
+        AdbClient::new("127.0.0.1", port)
+    }
+
+    /// The fields of a v2 stat after its error, with an mtime of 1700000000.
+    fn stat_v2(mode: u32, size: u64) -> Vec<u8> {
+        let mut buf = vec![0u8; 16];
+        buf.extend_from_slice(&mode.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 12]);
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 8]);
+        buf.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 8]);
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_sync_stat_list_pull() {
+        let client = fake_sync_server(vec![
+            ("/sdcard/DCIM/a.jpg", b"jpeg"),
+            ("/sdcard/DCIM/Camera/b.mp4", b"video data"),
+        ], Vec::new(), false).await;
+
+        let stat = client.stat("emulator-5554", "/sdcard/DCIM/a.jpg").await.unwrap();
+        assert!(stat.is_file());
+        assert_eq!(stat.size, 4);
+        assert!(client.stat("emulator-5554", "/sdcard/DCIM").await.unwrap().is_dir());
+        assert_eq!(client.stat("emulator-5554", "/sdcard/missing").await.unwrap().mode, 0);
+
+        let entries = client.list_dir("emulator-5554", "/sdcard/DCIM").await.unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a.jpg", "Camera"]);
+        assert!(entries[1].stat.is_dir());
+
+        let local = std::env::temp_dir().join(format!("scrcpy_gui_pull_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&local);
+        let mut chunks = 0;
+        let summary = crate::transfer::pull_dir(&client, "emulator-5554", "/sdcard/DCIM", &local, &mut |_, _| chunks += 1).await.unwrap();
+        assert_eq!(chunks, 2);
+        assert_eq!((summary.files, summary.bytes), (2, 14));
+        assert!(summary.failures.is_empty());
+        assert_eq!(std::fs::read(local.join("Camera").join("b.mp4")).unwrap(), b"video data");
+
+        let missing = local.join("missing.txt");
+        let err = client.pull("emulator-5554", "/sdcard/missing.txt", &missing, &mut |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("No such file"));
+        assert!(!missing.exists());
+        let _ = std::fs::remove_dir_all(&local);
+    }
+
+    #[tokio::test]
+    async fn test_sync_v2_sizes() {
+        let large = 5 * 1024 * 1024 * 1024;
+        let client = fake_sync_server(vec![("/sdcard/Movies/clip.mp4", b"clip")], vec![("/sdcard/Movies/qa.mkv", large)], true).await;
+
+        let stat = client.stat("emulator-5554", "/sdcard/Movies/qa.mkv").await.unwrap();
+        assert_eq!((stat.size, stat.mtime), (large, 1_700_000_000));
+        assert!(stat.is_file());
+        assert!(client.stat("emulator-5554", "/sdcard/Movies").await.unwrap().is_dir());
+        assert_eq!(client.stat("emulator-5554", "/sdcard/missing").await.unwrap().mode, 0);
+
+        let entries = client.list_dir("emulator-5554", "/sdcard/Movies").await.unwrap();
+        let sizes: Vec<(&str, u64)> = entries.iter().map(|e| (e.name.as_str(), e.stat.size)).collect();
+        assert_eq!(sizes, vec![("clip.mp4", 4), ("qa.mkv", large)]);
+
+        // Without the features only the low 32 bits survive
+        let v1 = fake_sync_server(Vec::new(), vec![("/sdcard/Movies/qa.mkv", large)], false).await;
+        assert_eq!(v1.stat("emulator-5554", "/sdcard/Movies/qa.mkv").await.unwrap().size, large & 0xffff_ffff);
+    }
+
+    #[test]
+    fn test_parse_shell_output() {
+        let output = parse_shell_output(&format!("no newline\n{}0\n", EXIT_MARKER));
+        assert_eq!(output, ShellOutput { output: "no newline".to_string(), exit_code: Some(0) });
+
+        let pty = parse_shell_output(&format!("line\r\n\r\n{}127\r\n", EXIT_MARKER));
+        assert_eq!(pty, ShellOutput { output: "line\r\n".to_string(), exit_code: Some(127) });
+
+        let killed = parse_shell_output("partial");
+        assert_eq!(killed.exit_code, None);
+        assert_eq!(killed.into_result(), Err("partial".to_string()));
+        assert_eq!(ShellOutput { output: String::new(), exit_code: Some(2) }.into_result(), Err("Command exited with code 2".to_string()));
+    }
+
+    #[test]
+    fn test_adb_server_settings() {
+        let server = AdbServer::default();
+        assert!(server.is_default());
+        assert_eq!(server.socket_spec(), "tcp:127.0.0.1:5037");
+
+        let path = std::env::temp_dir().join(format!("scrcpy_gui_adb_server_{}.toml", std::process::id()));
+        let tunnel = AdbServer { host: "localhost".to_string(), port: 15037 };
+        tunnel.save(&path).unwrap();
+        assert_eq!(AdbServer::load(&path).unwrap(), tunnel);
+
+        // Missing fields fall back to the defaults
+        std::fs::write(&path, "port = 5038\n").unwrap();
+        assert_eq!(AdbServer::load(&path).unwrap(), AdbServer { port: 5038, ..AdbServer::default() });
+        let _ = std::fs::remove_file(&path);
+
+        assert!(AdbServer { host: " ".to_string(), port: 5037 }.validate().is_err());
+        assert!(AdbServer { port: 0, ..AdbServer::default() }.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_is_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let err = AdbClient::new("127.0.0.1", port).devices().await.unwrap_err();
+        assert!(matches!(err, AdbError::Unavailable(_)));
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+
+const KNOWN_ABIS: [&str; 7] = ["arm64-v8a", "armeabi-v7a", "armeabi", "x86_64", "x86", "mips64", "mips"];
+const DENSITIES: [(&str, u32); 7] = [
+    ("ldpi", 120),
+    ("mdpi", 160),
+    ("tvdpi", 213),
+    ("hdpi", 240),
+    ("xhdpi", 320),
+    ("xxhdpi", 480),
+    ("xxxhdpi", 640),
+];
+
+/// What `install_apk` was handed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleKind {
+    /// bundletool or SAI split set.
+    Apks,
+    /// APKPure style archive, possibly with OBB expansion files.
+    Xapk,
+    /// A folder of split APKs, e.g. pulled with `pm path`.
+    Directory,
+}
+
+/// `None` for a plain APK.
+pub fn bundle_kind(path: &Path) -> Option<BundleKind> {
+    if path.is_dir() {
+        return Some(BundleKind::Directory);
+    }
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "apks" => Some(BundleKind::Apks),
+        "xapk" => Some(BundleKind::Xapk),
+        _ => None,
+    }
+}
+
+/// The device properties split selection depends on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceSpec {
+    /// Preferred first, as in `ro.product.cpu.abilist`.
+    pub abis: Vec<String>,
+    pub density: Option<u32>,
+    /// Language codes, e.g. `en`.
+    pub languages: Vec<String>,
+}
+
+impl DeviceSpec {
+    /// Builds the spec from `getprop` output and the `wm density` value.
+    pub fn from_props(props: &HashMap<String, String>, density: Option<u32>) -> Self {
+        let prop = |key: &str| props.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let abis = prop("ro.product.cpu.abilist")
+            .or_else(|| prop("ro.product.cpu.abi"))
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+        let density = density.or_else(|| prop("ro.sf.lcd_density").and_then(|v| v.parse().ok()));
+        let languages = ["persist.sys.locale", "ro.product.locale", "persist.sys.language", "ro.product.locale.language"]
+            .iter()
+            .filter_map(|key| prop(key))
+            .filter_map(|locale| locale.split(['-', '_']).next().map(str::to_lowercase))
+            .take(1)
+            .collect();
+        Self { abis, density, languages }
+    }
+}
+
+/// Device configuration a split APK targets, read from its file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitConfig {
+    Abi(String),
+    Density(u32),
+    Language(String),
+    /// Base and feature APKs, always installed.
+    Other,
+}
+
+fn is_language(qualifier: &str) -> bool {
+    let language = qualifier.split(['-', '_']).next().unwrap_or("");
+    (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// Classifies `split_config.arm64_v8a.apk` (installed splits), `config.xxhdpi.apk`
+/// (XAPK), `split_feature.config.en.apk` (feature splits) and `base-arm64_v8a.apk` (bundletool).
+pub fn classify(file_name: &str) -> SplitConfig {
+    let stem = file_name.strip_suffix(".apk").unwrap_or(file_name);
+    let qualifier = if let Some(q) = stem.strip_prefix("split_config.").or_else(|| stem.strip_prefix("config.")) {
+        q
+    } else if let Some((_, q)) = stem.strip_prefix("split_").and_then(|s| s.split_once(".config.")) {
+        q
+    } else if let Some((_, q)) = stem.split_once('-').filter(|_| !stem.contains('.')) {
+        q
+    } else {
+        return SplitConfig::Other;
+    };
+
+    // Split names spell ABIs with underscores: arm64_v8a, x86_64
+    if let Some(abi) = KNOWN_ABIS.iter().find(|abi| abi.replace('-', "_") == qualifier) {
+        return SplitConfig::Abi(abi.to_string());
+    }
+    if let Some((_, dpi)) = DENSITIES.iter().find(|(name, _)| *name == qualifier) {
+        return SplitConfig::Density(*dpi);
+    }
+    if qualifier != "master" && is_language(qualifier) {
+        return SplitConfig::Language(qualifier.split(['-', '_']).next().unwrap_or(qualifier).to_string());
+    }
+    SplitConfig::Other
+}
+
+/// Which splits of a set to install.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitSelection {
+    pub selected: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// Picks the splits for `spec`: the most preferred ABI the set has, the
+/// closest density at or above the device's (like the framework does), and
+/// the device language. Every split of a kind is kept when the device doesn't
+/// report that property, since pm accepts redundant config splits.
+pub fn select_splits(names: &[String], spec: &DeviceSpec) -> SplitSelection {
+    let configs: Vec<SplitConfig> = names.iter().map(|n| classify(file_name(n))).collect();
+
+    let abis: BTreeSet<&str> = configs.iter().filter_map(|c| match c { SplitConfig::Abi(a) => Some(a.as_str()), _ => None }).collect();
+    let abi = spec.abis.iter().find(|a| abis.contains(a.as_str()));
+
+    let densities: BTreeSet<u32> = configs.iter().filter_map(|c| match c { SplitConfig::Density(d) => Some(*d), _ => None }).collect();
+    let density = spec.density.and_then(|target| {
+        densities.iter().find(|d| **d >= target).or_else(|| densities.iter().next_back()).copied()
+    });
+
+    let mut selection = SplitSelection::default();
+    for (name, config) in names.iter().zip(&configs) {
+        let keep = match config {
+            SplitConfig::Abi(a) => spec.abis.is_empty() || abi == Some(a),
+            SplitConfig::Density(d) => spec.density.is_none() || density == Some(*d),
+            SplitConfig::Language(l) => spec.languages.is_empty() || spec.languages.contains(l),
+            SplitConfig::Other => true,
+        };
+        if keep {
+            selection.selected.push(name.clone());
+        } else {
+            selection.skipped.push(name.clone());
+        }
+    }
+    selection
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// APK entries of an archive worth considering. bundletool's standalone APKs
+/// are only for pre-Lollipop devices and only used when there are no splits.
+pub fn apk_entries(names: &[String]) -> Vec<String> {
+    let apks: Vec<&String> = names.iter().filter(|n| n.to_lowercase().ends_with(".apk")).collect();
+    let splits: Vec<String> = apks.iter().filter(|n| !n.starts_with("standalones/")).map(|n| n.to_string()).collect();
+    if splits.is_empty() { apks.into_iter().cloned().collect() } else { splits }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct XapkManifest {
+    #[serde(default)]
+    package_name: String,
+    #[serde(default)]
+    expansions: Vec<XapkExpansion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XapkExpansion {
+    file: String,
+    install_path: String,
+}
+
+/// An OBB expansion file and where it goes on the device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObbFile {
+    pub entry: String,
+    pub remote_path: String,
+}
+
+/// OBB placement from an XAPK's `manifest.json`, falling back to entries
+/// already laid out under `Android/obb/`.
+pub fn obb_files(manifest: Option<&str>, names: &[String]) -> Vec<ObbFile> {
+    let manifest: XapkManifest = manifest.and_then(|m| serde_json::from_str(m).ok()).unwrap_or_default();
+    if !manifest.expansions.is_empty() {
+        return manifest
+            .expansions
+            .into_iter()
+            .map(|e| ObbFile { entry: e.file, remote_path: format!("/sdcard/{}", e.install_path.trim_start_matches('/')) })
+            .collect();
+    }
+
+    names
+        .iter()
+        .filter(|n| n.to_lowercase().ends_with(".obb"))
+        .filter_map(|n| {
+            let remote_path = if n.starts_with("Android/obb/") {
+                format!("/sdcard/{}", n)
+            } else if !manifest.package_name.is_empty() {
+                format!("/sdcard/Android/obb/{}/{}", manifest.package_name, file_name(n))
+            } else {
+                return None;
+            };
+            Some(ObbFile { entry: n.clone(), remote_path })
+        })
+        .collect()
+}
+
+/// A split set ready for `adb install-multiple`. Files unpacked from an archive
+/// live in `workdir`, which is removed on drop.
+#[derive(Debug)]
+pub struct PreparedBundle {
+    pub apks: Vec<PathBuf>,
+    /// Local OBB file and its device path.
+    pub obbs: Vec<(PathBuf, String)>,
+    pub selection: SplitSelection,
+    workdir: Option<PathBuf>,
+}
+
+impl PreparedBundle {
+    pub fn total_size(&self) -> u64 {
+        self.apks.iter().chain(self.obbs.iter().map(|(p, _)| p)).filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum()
+    }
+}
+
+impl Drop for PreparedBundle {
+    fn drop(&mut self) {
+        if let Some(workdir) = &self.workdir {
+            let _ = std::fs::remove_dir_all(workdir);
+        }
+    }
+}
+
+/// Returned by `prepare` when `cancel` was set.
+pub const PREPARE_CANCELLED: &str = "Extraction cancelled";
+
+/// Copies in chunks, so a single large OBB doesn't hold up a cancel.
+fn copy_unless_cancelled(reader: &mut impl Read, writer: &mut impl Write, cancel: &AtomicBool) -> Result<(), String> {
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(PREPARE_CANCELLED.to_string());
+        }
+        let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+    }
+}
+
+fn extract_entry(archive: &mut zip::ZipArchive<File>, name: &str, workdir: &Path, cancel: &AtomicBool) -> Result<PathBuf, String> {
+    let mut entry = archive.by_name(name).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    // Entries are flattened, split names are unique within a set
+    let local = workdir.join(file_name(name));
+    let mut out = File::create(&local).map_err(|e| format!("Failed to create {}: {}", local.display(), e))?;
+    copy_unless_cancelled(&mut entry, &mut out, cancel).map_err(|e| match e.as_str() {
+        PREPARE_CANCELLED => e,
+        _ => format!("Failed to extract {}: {}", name, e),
+    })?;
+    Ok(local)
+}
+
+/// Unpacks (for archives) and selects the splits of `path` for the device.
+/// Blocking, archives with OBBs can be several gigabytes, so extraction stops
+/// with `PREPARE_CANCELLED` once `cancel` is set. Nothing is left in `workdir` then.
+pub fn prepare(path: &Path, kind: BundleKind, spec: &DeviceSpec, workdir: &Path, cancel: &AtomicBool) -> Result<PreparedBundle, String> {
+    if kind == BundleKind::Directory {
+        let mut names: Vec<String> = std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.to_lowercase().ends_with(".apk"))
+            .collect();
+        names.sort();
+        let selection = select_splits(&names, spec);
+        let apks = selection.selected.iter().map(|n| path.join(n)).collect();
+        return check_prepared(PreparedBundle { apks, obbs: Vec::new(), selection, workdir: None });
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+    let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+
+    std::fs::create_dir_all(workdir).map_err(|e| e.to_string())?;
+    let mut bundle = PreparedBundle {
+        apks: Vec::new(),
+        obbs: Vec::new(),
+        selection: select_splits(&apk_entries(&names), spec),
+        workdir: Some(workdir.to_path_buf()),
+    };
+
+    for name in bundle.selection.selected.clone() {
+        let local = extract_entry(&mut archive, &name, workdir, cancel)?;
+        bundle.apks.push(local);
+    }
+
+    if kind == BundleKind::Xapk {
+        let manifest = archive.by_name("manifest.json").ok().and_then(|mut entry| {
+            let mut manifest = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut manifest).ok().map(|_| manifest)
+        });
+        for obb in obb_files(manifest.as_deref(), &names) {
+            let local = extract_entry(&mut archive, &obb.entry, workdir, cancel)?;
+            bundle.obbs.push((local, obb.remote_path));
+        }
+    }
+    check_prepared(bundle)
+}
+
+fn check_prepared(bundle: PreparedBundle) -> Result<PreparedBundle, String> {
+    if bundle.apks.is_empty() {
+        return Err("No APKs found in the split set".to_string());
+    }
+    Ok(bundle)
+}
+
+/// Bundles split APKs into an `.apks` archive, named like an installed set
+/// (`base.apk`, `split_config.*.apk`) so `prepare` can install it again.
+/// APKs are already compressed, so the entries are stored as is.
+pub fn write_apks(files: &[PathBuf], dest: &Path) -> Result<(), String> {
+    let out = File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let mut writer = zip::ZipWriter::new(out);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    let result = (|| {
+        for file in files {
+            let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            writer.start_file(name.as_str(), options).map_err(|e| format!("Failed to add {}: {}", name, e))?;
+            let mut input = File::open(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+            std::io::copy(&mut input, &mut writer).map_err(|e| format!("Failed to add {}: {}", name, e))?;
+        }
+        writer.finish().map(|_| ()).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(dest);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("split_config.arm64_v8a.apk"), SplitConfig::Abi("arm64-v8a".to_string()));
+        assert_eq!(classify("config.xxhdpi.apk"), SplitConfig::Density(480));
+        assert_eq!(classify("split_config.x86_64.apk"), SplitConfig::Abi("x86_64".to_string()));
+        assert_eq!(classify("split_camera.config.fr.apk"), SplitConfig::Language("fr".to_string()));
+        assert_eq!(classify("base-pt_BR.apk"), SplitConfig::Language("pt".to_string()));
+        assert_eq!(classify("base-master.apk"), SplitConfig::Other);
+        assert_eq!(classify("com.example.app.apk"), SplitConfig::Other);
+        assert_eq!(classify("split_camera.apk"), SplitConfig::Other);
+    }
+
+    #[test]
+    fn test_select_splits() {
+        let spec = DeviceSpec {
+            abis: vec!["arm64-v8a".to_string(), "armeabi-v7a".to_string()],
+            density: Some(420),
+            languages: vec!["en".to_string()],
+        };
+        let set = names(&[
+            "splits/base-master.apk",
+            "splits/base-armeabi_v7a.apk",
+            "splits/base-arm64_v8a.apk",
+            "splits/base-xhdpi.apk",
+            "splits/base-xxhdpi.apk",
+            "splits/base-xxxhdpi.apk",
+            "splits/base-en.apk",
+            "splits/base-de.apk",
+        ]);
+        let selection = select_splits(&set, &spec);
+        assert_eq!(selection.selected, names(&["splits/base-master.apk", "splits/base-arm64_v8a.apk", "splits/base-xxhdpi.apk", "splits/base-en.apk"]));
+        assert_eq!(selection.skipped.len(), 4);
+
+        // Nothing known about the device: install everything
+        assert_eq!(select_splits(&set, &DeviceSpec::default()).selected, set);
+    }
+
+    #[test]
+    fn test_device_spec_from_props() {
+        let props: HashMap<String, String> = [
+            ("ro.product.cpu.abilist".to_string(), "arm64-v8a,armeabi-v7a,armeabi".to_string()),
+            ("persist.sys.locale".to_string(), "de-DE".to_string()),
+            ("ro.sf.lcd_density".to_string(), "560".to_string()),
+        ]
+        .into();
+        let spec = DeviceSpec::from_props(&props, None);
+        assert_eq!(spec.abis[0], "arm64-v8a");
+        assert_eq!(spec.density, Some(560));
+        assert_eq!(spec.languages, vec!["de"]);
+    }
+
+    #[test]
+    fn test_write_apks_round_trip() {
+        let dir = std::env::temp_dir().join(format!("scrcpy_gui_apks_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let files: Vec<PathBuf> = ["base.apk", "split_config.arm64_v8a.apk", "split_config.x86_64.apk"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::write(&path, name.as_bytes()).unwrap();
+                path
+            })
+            .collect();
+
+        let archive = dir.join("app.apks");
+        write_apks(&files, &archive).unwrap();
+        let spec = DeviceSpec { abis: vec!["arm64-v8a".to_string()], ..Default::default() };
+        let bundle = prepare(&archive, BundleKind::Apks, &spec, &dir.join("extracted"), &AtomicBool::new(false)).unwrap();
+        assert_eq!(bundle.selection.selected, vec!["base.apk", "split_config.arm64_v8a.apk"]);
+        assert_eq!(std::fs::read(&bundle.apks[1]).unwrap(), b"split_config.arm64_v8a.apk");
+
+        drop(bundle);
+        assert!(!dir.join("extracted").exists());
+
+        let cancelled = prepare(&archive, BundleKind::Apks, &spec, &dir.join("cancelled"), &AtomicBool::new(true));
+        assert_eq!(cancelled.unwrap_err(), PREPARE_CANCELLED);
+        assert!(!dir.join("cancelled").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apk_entries_and_obbs() {
+        let archive = names(&["toc.pb", "standalones/standalone-arm64_v8a.apk", "splits/base-master.apk", "manifest.json", "Android/obb/com.game/main.7.com.game.obb"]);
+        assert_eq!(apk_entries(&archive), names(&["splits/base-master.apk"]));
+
+        let manifest = r#"{"package_name": "com.game", "expansions": [{"file": "Android/obb/com.game/main.7.com.game.obb", "install_location": "EXTERNAL_STORAGE", "install_path": "Android/obb/com.game/main.7.com.game.obb"}]}"#;
+        let expected = vec![ObbFile {
+            entry: "Android/obb/com.game/main.7.com.game.obb".to_string(),
+            remote_path: "/sdcard/Android/obb/com.game/main.7.com.game.obb".to_string(),
+        }];
+        assert_eq!(obb_files(Some(manifest), &archive), expected);
+        assert_eq!(obb_files(None, &archive), expected);
+    }
+}
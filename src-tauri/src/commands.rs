@@ -1,14 +1,36 @@
-use tauri::{State, Window, Emitter, Manager};
-use std::process::{Command as StdCommand, Stdio};
+use tauri::{State, Window, Emitter, Manager, Runtime};
+use std::process::Stdio;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use serde_json::json;
-use crate::ScrcpyState;
+use crate::{ScrcpyState, DeviceWatcherState, TransferQueueState, TransferState};
+use crate::adb::{self, AdbClient, AdbError, AdbServer, ShellOutput};
+use crate::device::{
+    parse_device_list, diff_devices, Device, DeviceChange, DeviceInfo,
+    parse_getprop, parse_wm_size, parse_wm_density, parse_battery, suggest_defaults,
+};
 use tokio::process::Command as TokioCommand;
 use tokio::io::{BufReader, AsyncBufReadExt};
 use tokio::time::{timeout, Duration};
-use serde::Deserialize;
+use crate::config::{MaxSize, ScrcpyArgsBuilder, ScrcpyConfig};
+use crate::profiles::ProfileStore;
+use crate::session::{supervise, ExitInfo, ScrcpySession, SessionControl, SessionInfo, StderrTail, StopAllReport, StopFailure, StopReport, DEFAULT_STOP_GRACE};
+use crate::recording;
+use crate::processes;
+use crate::transfer::{self, ProgressTracker, TransferKind, TransferProgress, TransferStatus, TransferSummary};
+use crate::transfer_queue::{self, QueueItem, MAX_ATTEMPTS};
+use crate::remote_files::{self, shell_quote, RemoteEntry};
+use crate::dir_sync::{self, CompareMode, SyncAction, SyncReport};
+use crate::apk_bundle::{self, DeviceSpec, PreparedBundle};
+use crate::install::{InstallError, InstallFailure, InstallOptions};
+use crate::packages::{self, check_package_name, ApkBackup, PackageFilter, PackageInfo};
+use tokio::sync::{mpsc, oneshot};
+use crate::scrcpy_options::{self, App, Camera, Display, Encoder};
+use crate::scrcpy_log;
 use flate2::read::GzDecoder;
 use tar::Archive;
 
@@ -70,15 +92,58 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result
 
 
 #[cfg(target_os = "windows")]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
+pub(crate) const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+const ADB_SERVER_FILE: &str = "adb_server.toml";
 
 fn create_command<S: AsRef<std::ffi::OsStr>>(program: S) -> TokioCommand {
     let mut cmd = TokioCommand::new(program);
     #[cfg(target_os = "windows")]
     cmd.creation_flags(CREATE_NO_WINDOW);
+
+    // Point adb, and scrcpy's own adb calls, at the configured server
+    let server = adb::server();
+    if !server.is_default() {
+        cmd.env("ADB_SERVER_SOCKET", server.socket_spec());
+        cmd.env("ANDROID_ADB_SERVER_PORT", server.port.to_string());
+    }
     cmd
 }
 
+fn adb_server_path(app_handle: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    let dir = app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(ADB_SERVER_FILE))
+}
+
+/// Restores the adb server saved with `set_adb_server`, called once at startup.
+pub fn load_adb_server(app_handle: &tauri::AppHandle) {
+    if let Ok(path) = adb_server_path(app_handle) {
+        if let Ok(server) = AdbServer::load(&path) {
+            adb::set_server(server);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_adb_server() -> AdbServer {
+    adb::server()
+}
+
+/// Changes (and persists) the adb server used by every command and new scrcpy
+/// session. Unset fields go back to the local default.
+#[tauri::command]
+pub fn set_adb_server(app_handle: tauri::AppHandle, host: Option<String>, port: Option<u16>) -> Result<AdbServer, String> {
+    let defaults = AdbServer::default();
+    let server = AdbServer {
+        host: host.map(|h| h.trim().to_string()).unwrap_or(defaults.host),
+        port: port.unwrap_or(defaults.port),
+    };
+    server.validate()?;
+    server.save(&adb_server_path(&app_handle)?)?;
+    adb::set_server(server.clone());
+    Ok(server)
+}
+
 #[tauri::command]
 pub async fn check_scrcpy(custom_path: Option<String>) -> serde_json::Value {
     let exe_path = get_binary_path("scrcpy", custom_path);
@@ -104,10 +169,17 @@ pub async fn check_scrcpy(custom_path: Option<String>) -> serde_json::Value {
 
 #[tauri::command]
 pub async fn get_devices(custom_path: Option<String>) -> serde_json::Value {
+    match AdbClient::default().devices().await {
+        Ok(devices) => return json!({ "error": false, "devices": devices }),
+        Err(AdbError::Failed(e)) => return json!({ "error": true, "message": e }),
+        Err(AdbError::Unavailable(_)) => {} // Fall back to the CLI, which also starts the server
+    }
+
     let adb_path = get_binary_path("adb", custom_path);
     
     let output = create_command(&adb_path)
         .arg("devices")
+        .arg("-l")
         .output()
         .await;
 
@@ -115,12 +187,7 @@ pub async fn get_devices(custom_path: Option<String>) -> serde_json::Value {
         Ok(o) => {
              if o.status.success() {
                  let out_str = String::from_utf8_lossy(&o.stdout);
-                 let devices: Vec<String> = out_str.lines()
-                    .skip(1) // Skip "List of devices attached"
-                    .filter(|l| l.contains("\tdevice"))
-                    .map(|l| l.split('\t').next().unwrap_or("").trim().to_string())
-                    .filter(|s| !s.is_empty() && !s.contains("._tcp") && !s.contains("._udp"))
-                    .collect();
+                 let devices = parse_device_list(&out_str);
                  
                  json!({ "error": false, "devices": devices })
              } else {
@@ -133,6 +200,75 @@ pub async fn get_devices(custom_path: Option<String>) -> serde_json::Value {
     }
 }
 
+fn emit_device_change(window: &Window, change: DeviceChange) {
+    match change {
+        DeviceChange::Added(device) => {
+            let _ = window.emit("device-added", device);
+        }
+        DeviceChange::Removed(device) => {
+            let _ = window.emit("device-removed", device);
+        }
+        DeviceChange::StateChanged { device, previous } => {
+            let _ = window.emit("device-state-changed", json!({ "device": device, "previous": previous }));
+        }
+    }
+}
+
+async fn watch_devices(window: Window, adb_path: String) {
+    let mut known: Vec<Device> = Vec::new();
+
+    loop {
+        let mut stream = match AdbClient::default().track_devices().await {
+            Ok(s) => s,
+            Err(AdbError::Unavailable(_)) => {
+                // Server isn't up yet, let the CLI spawn it and try again
+                let _ = create_command(&adb_path).arg("start-server").output().await;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+            Err(e) => {
+                let _ = window.emit("scrcpy-log", format!("[SYSTEM] Device watcher error: {}", e));
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                continue;
+            }
+        };
+
+        // The server pushes the full device list every time something changes
+        while let Ok(payload) = adb::read_hex_payload(&mut stream).await {
+            let devices = parse_device_list(&payload);
+            for change in diff_devices(&known, &devices) {
+                emit_device_change(&window, change);
+            }
+            known = devices;
+        }
+
+        // Connection dropped (e.g. kill_adb), every device is gone as far as we know
+        for device in known.drain(..) {
+            emit_device_change(&window, DeviceChange::Removed(device));
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+#[tauri::command]
+pub async fn start_device_watcher(window: Window, state: State<'_, DeviceWatcherState>, custom_path: Option<String>) -> Result<(), String> {
+    let adb_path = get_binary_path("adb", custom_path);
+    let handle = tokio::spawn(watch_devices(window, adb_path));
+
+    if let Some(previous) = state.task.lock().unwrap().replace(handle) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_device_watcher(state: State<'_, DeviceWatcherState>) -> Result<(), String> {
+    if let Some(handle) = state.task.lock().unwrap().take() {
+        handle.abort();
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_mdns_devices(custom_path: Option<String>) -> serde_json::Value {
     let adb_path = get_binary_path("adb", custom_path);
@@ -181,6 +317,23 @@ pub async fn get_mdns_devices(custom_path: Option<String>) -> serde_json::Value
 pub async fn adb_connect(window: Window, ip: String, custom_path: Option<String>) -> Result<serde_json::Value, String> {
     let adb_path = get_binary_path("adb", custom_path);
     let _ = window.emit("scrcpy-log", format!("[SYSTEM] Attempting wireless connection to {}...", ip));
+
+    match timeout(Duration::from_secs(5), AdbClient::default().connect_device(&ip)).await {
+        Ok(Ok(message)) => {
+            let _ = window.emit("scrcpy-log", format!("[ADB] {}", message));
+            let success = !message.contains("cannot connect") && !message.contains("failed");
+            return Ok(json!({ "success": success, "message": message }));
+        }
+        Ok(Err(AdbError::Failed(e))) => {
+            let _ = window.emit("scrcpy-log", format!("[ADB ERROR] {}", e));
+            return Ok(json!({ "success": false, "message": e }));
+        }
+        Ok(Err(AdbError::Unavailable(_))) => {} // Fall back to the CLI below
+        Err(_) => {
+            let _ = window.emit("scrcpy-log", format!("[SYSTEM] Connection to {} timed out after 5s.", ip));
+            return Ok(json!({ "success": false, "message": "connection timed out" }));
+        }
+    }
     
     let child = create_command(&adb_path)
         .arg("connect")
@@ -239,6 +392,12 @@ pub async fn adb_pair(window: Window, ip: String, code: String, custom_path: Opt
 
 #[tauri::command]
 pub async fn adb_shell(device: String, command: String, custom_path: Option<String>) -> serde_json::Value {
+    match AdbClient::default().shell(&device, &command).await {
+        Ok(shell) => return json!({ "success": shell.success(), "output": shell.output }),
+        Err(AdbError::Failed(e)) => return json!({ "success": false, "message": e }),
+        Err(AdbError::Unavailable(_)) => {}
+    }
+
     let adb_path = get_binary_path("adb", custom_path);
     
     let output = create_command(&adb_path)
@@ -257,6 +416,71 @@ pub async fn adb_shell(device: String, command: String, custom_path: Option<Stri
     }
 }
 
+/// Runs a device shell command natively, falling back to the adb binary when the server is unreachable.
+async fn shell_output(adb_path: &str, device: &str, command: &str) -> Result<String, String> {
+    match AdbClient::default().shell(device, command).await {
+        Ok(shell) => return shell.into_result(),
+        Err(AdbError::Failed(e)) => return Err(e),
+        Err(AdbError::Unavailable(_)) => {}
+    }
+
+    let output = create_command(adb_path)
+        .arg("-s")
+        .arg(device)
+        .arg("shell")
+        .arg(command)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    // Commands redirecting 2>&1 report their errors on stdout
+    let output = ShellOutput {
+        output: if output.status.success() || stderr.trim().is_empty() { stdout } else { stderr },
+        exit_code: output.status.code(),
+    };
+    output.into_result()
+}
+
+#[tauri::command]
+pub async fn get_device_info(device: String, custom_path: Option<String>) -> Result<DeviceInfo, String> {
+    let adb_path = get_binary_path("adb", custom_path);
+
+    let (props, size, density, battery, timeout_setting) = tokio::join!(
+        shell_output(&adb_path, &device, "getprop"),
+        shell_output(&adb_path, &device, "wm size"),
+        shell_output(&adb_path, &device, "wm density"),
+        shell_output(&adb_path, &device, "dumpsys battery"),
+        shell_output(&adb_path, &device, "settings get system screen_off_timeout"),
+    );
+
+    // getprop is the only source we can't do without
+    let props = parse_getprop(&props?);
+    let prop = |key: &str| props.get(key).filter(|v| !v.is_empty()).cloned();
+
+    let (screen_width, screen_height) = size.ok().and_then(|s| parse_wm_size(&s)).unzip();
+    let density = density.ok().and_then(|d| parse_wm_density(&d));
+    let sdk_level = prop("ro.build.version.sdk").and_then(|v| v.parse().ok());
+
+    Ok(DeviceInfo {
+        serial: device,
+        android_version: prop("ro.build.version.release"),
+        sdk_level,
+        manufacturer: prop("ro.product.manufacturer"),
+        model: prop("ro.product.model"),
+        abis: prop("ro.product.cpu.abilist")
+            .map(|v| v.split(',').map(|s| s.to_string()).collect())
+            .unwrap_or_default(),
+        screen_width,
+        screen_height,
+        density,
+        battery: battery.ok().and_then(|b| parse_battery(&b)),
+        screen_off_timeout_ms: timeout_setting.ok().and_then(|v| v.trim().parse().ok()),
+        suggested: suggest_defaults(screen_width, screen_height, density, sdk_level),
+    })
+}
+
 #[tauri::command]
 pub async fn run_terminal_command(device: Option<String>, cmd: String, custom_path: Option<String>) -> serde_json::Value {
     let mut parts = split_args(&cmd).unwrap_or_else(|_| cmd.split_whitespace().map(|s| s.to_string()).collect());
@@ -342,90 +566,1062 @@ fn split_args(s: &str) -> Result<Vec<String>, String> {
     Ok(args)
 }
 
+const DEFAULT_PUSH_DIR: &str = "/sdcard/Download/";
+/// Where APKs are staged before `pm install`.
+const REMOTE_TMP_DIR: &str = "/data/local/tmp";
+
+/// Removes a transfer's cancel handle once it's over, however it ends.
+struct TransferGuard<'a> {
+    state: &'a TransferState,
+    id: String,
+    generation: u64,
+}
+
+impl Drop for TransferGuard<'_> {
+    fn drop(&mut self) {
+        self.state.transfers.lock().unwrap().release(&self.id, self.generation);
+    }
+}
+
+/// Registers a cancellable transfer, keeping the frontend's id when it picked
+/// one. Fails when that id belongs to a transfer that's still running, or
+/// could belong to a push queue item.
+fn begin_transfer(state: &TransferState, transfer_id: Option<String>) -> Result<(TransferGuard<'_>, oneshot::Receiver<()>), String> {
+    if transfer_id.as_deref().is_some_and(transfer_queue::is_queue_id) {
+        return Err(format!("Transfer ids starting with {} are reserved for the push queue", transfer_queue::QUEUE_ID_PREFIX));
+    }
+    let (id, generation, cancel) = state.transfers.lock().unwrap().register(transfer_id)?;
+    Ok((TransferGuard { state, id, generation }, cancel))
+}
+
+/// Runs a transfer step, returning `None` if the transfer was cancelled first.
+/// CLI steps must use `kill_on_drop` so cancelling also stops the adb process.
+async fn cancellable<T>(cancel: &mut oneshot::Receiver<()>, work: impl std::future::Future<Output = T>) -> Option<T> {
+    tokio::select! {
+        result = work => Some(result),
+        _ = cancel => None,
+    }
+}
+
+fn emit_progress<R: Runtime>(emitter: &impl Emitter<R>, progress: TransferProgress) {
+    let _ = emitter.emit("transfer-progress", progress);
+}
+
 #[tauri::command]
-pub async fn push_file(device: String, file_path: String, custom_path: Option<String>) -> serde_json::Value {
+pub async fn cancel_transfer(transfers: State<'_, TransferState>, transfer_id: String) -> Result<(), String> {
+    if transfers.transfers.lock().unwrap().cancel(&transfer_id) {
+        Ok(())
+    } else {
+        Err(format!("No transfer in progress with id {}", transfer_id))
+    }
+}
+
+/// Pushes to `remote_path`, into it when it's a directory. Returns the final remote path and size.
+async fn native_push(
+    device: &str,
+    local: &Path,
+    remote_path: &str,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<(String, u64), AdbError> {
+    let client = AdbClient::default();
+    let name = local.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    let is_dir = remote_path.ends_with('/') || client.stat(device, remote_path).await?.is_dir();
+    let remote = if is_dir { adb::remote_join(remote_path, &name) } else { remote_path.to_string() };
+    let bytes = client.push(device, local, &remote, progress).await?;
+    Ok((remote, bytes))
+}
+
+/// Pushes one file natively, or through the CLI when the adb server can't be
+/// reached. Returns the remote path and size, or `TRANSFER_CANCELLED` when
+/// cancelled. The caller emits the final progress event with `finish_push`.
+async fn push_one<R: Runtime>(
+    emitter: &(impl Emitter<R> + Sync),
+    device: &str,
+    file_path: &str,
+    remote_path: &str,
+    adb_path: &str,
+    tracker: &mut ProgressTracker,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<(String, Option<u64>), String> {
+    let native = cancellable(cancel, native_push(device, Path::new(file_path), remote_path, &mut |n| {
+        if let Some(progress) = tracker.advance(n) { emit_progress(emitter, progress); }
+    })).await;
+
+    let result = match native {
+        Some(Ok((remote, bytes))) => Ok((remote, Some(bytes))),
+        Some(Err(AdbError::Failed(e))) => Err(format!("Transfer failed: {}", e)),
+        Some(Err(AdbError::Unavailable(_))) => {
+            // Resolve the destination the way native_push does, so callers get the same path back
+            let is_dir = remote_path.ends_with('/')
+                || shell_output(adb_path, device, &format!("[ -d {} ] && echo dir", shell_quote(remote_path)))
+                    .await
+                    .is_ok_and(|o| o.trim() == "dir");
+            let name = Path::new(file_path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let remote = if is_dir { adb::remote_join(remote_path, &name) } else { remote_path.to_string() };
+
+            match cli_push(adb_path, device, Path::new(file_path), &remote, cancel).await {
+                Ok(bytes) => Ok((remote, bytes)),
+                Err(e) if e == TRANSFER_CANCELLED => Err(e),
+                Err(e) => Err(format!("Transfer failed: {}", e)),
+            }
+        }
+        None => Err(TRANSFER_CANCELLED.to_string()),
+    };
+    result
+}
+
+/// Emits the final progress event of a push.
+fn finish_push<R: Runtime>(emitter: &impl Emitter<R>, tracker: &ProgressTracker, result: &Result<(String, Option<u64>), String>) {
+    let progress = match result {
+        Ok(_) => tracker.snapshot(TransferStatus::Done, None),
+        Err(e) if e == TRANSFER_CANCELLED => tracker.snapshot(TransferStatus::Cancelled, Some(e.clone())),
+        Err(e) => tracker.snapshot(TransferStatus::Failed, Some(e.clone())),
+    };
+    emit_progress(emitter, progress);
+}
+
+/// Pushes a file to `remote_path`, `/sdcard/Download/` by default. A path ending
+/// in `/` or naming an existing directory receives the file under its own name.
+/// Reports `transfer-progress` events and can be aborted with `cancel_transfer`.
+#[tauri::command]
+pub async fn push_file(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    file_path: String,
+    remote_path: Option<String>,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let remote_path = remote_path.filter(|p| !p.trim().is_empty()).unwrap_or_else(|| DEFAULT_PUSH_DIR.to_string());
+    let total = std::fs::metadata(&file_path).ok().map(|m| m.len());
+    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Push, &file_path, total);
     let adb_path = get_binary_path("adb", custom_path);
-    
-    let output = create_command(&adb_path)
-        .arg("-s")
-        .arg(&device)
-        .arg("push")
-        .arg(&file_path)
-        .arg("/sdcard/Download/")
-        .output()
-        .await;
 
-    match output {
-         Ok(o) => {
-             if o.status.success() {
-                 json!({ "success": true, "message": "File pushed to Downloads" })
-             } else {
-                 json!({ "success": false, "message": "Transfer failed" })
-             }
-         },
-         Err(e) => json!({ "success": false, "message": e.to_string() })
+    let result = push_one(&window, &device, &file_path, &remote_path, &adb_path, &mut tracker, &mut cancel).await;
+    finish_push(&window, &tracker, &result);
+    Ok(match result {
+        Ok((remote, bytes)) => {
+            json!({ "success": true, "message": format!("File pushed to {}", remote), "remotePath": remote, "bytes": bytes, "transferId": transfer.id })
+        }
+        Err(e) => json!({ "success": false, "message": e, "transferId": transfer.id }),
+    })
+}
+
+/// Adds files to the backend push queue, which runs them with a per-device
+/// concurrency limit and retries dropped connections. Progress comes as
+/// `transfer-progress` events keyed by item id and status changes as `transfer-queue` events.
+#[tauri::command]
+pub async fn enqueue_push(
+    app_handle: tauri::AppHandle,
+    queue: State<'_, TransferQueueState>,
+    device: String,
+    local_paths: Vec<String>,
+    remote_dir: Option<String>,
+    custom_path: Option<String>,
+) -> Result<Vec<QueueItem>, String> {
+    if local_paths.is_empty() {
+        return Err("No files to push".to_string());
+    }
+    let remote_dir = remote_dir.filter(|p| !p.trim().is_empty()).unwrap_or_else(|| DEFAULT_PUSH_DIR.to_string());
+    let items = queue.queue.lock().unwrap().enqueue(&device, &local_paths, &remote_dir);
+    for item in &items {
+        let _ = app_handle.emit("transfer-queue", item);
     }
+
+    pump_queue(&app_handle, custom_path);
+    Ok(items)
 }
 
 #[tauri::command]
-pub async fn install_apk(device: String, file_path: String, custom_path: Option<String>) -> serde_json::Value {
+pub async fn list_transfer_queue(queue: State<'_, TransferQueueState>) -> Result<Vec<QueueItem>, String> {
+    Ok(queue.queue.lock().unwrap().items())
+}
+
+/// Sets how many pushes run at once on `device`, or on every device without its own limit.
+#[tauri::command]
+pub async fn set_queue_concurrency(
+    app_handle: tauri::AppHandle,
+    queue: State<'_, TransferQueueState>,
+    device: Option<String>,
+    concurrency: usize,
+    custom_path: Option<String>,
+) -> Result<(), String> {
+    queue.queue.lock().unwrap().set_limit(device.as_deref(), concurrency);
+    pump_queue(&app_handle, custom_path);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cancel_queue_item(
+    app_handle: tauri::AppHandle,
+    queue: State<'_, TransferQueueState>,
+    transfers: State<'_, TransferState>,
+    item_id: String,
+) -> Result<(), String> {
+    let cancelled = {
+        let mut queue = queue.queue.lock().unwrap();
+        if queue.get(&item_id).is_none() {
+            return Err(format!("No queued transfer with id {}", item_id));
+        }
+        // Running items have their transfer registered under this same lock, see pump_queue
+        match queue.cancel_queued(&item_id) {
+            Some(item) => item,
+            // Its worker reports the cancellation
+            None if transfers.transfers.lock().unwrap().cancel(&item_id) => return Ok(()),
+            None => return Err(format!("Transfer {} already finished", item_id)),
+        }
+    };
+    let _ = app_handle.emit("transfer-queue", cancelled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_finished_transfers(queue: State<'_, TransferQueueState>) -> Result<(), String> {
+    queue.queue.lock().unwrap().clear_finished();
+    Ok(())
+}
+
+/// A started queue item's transfer generation and cancel signal.
+type QueueRegistration = Result<(u64, oneshot::Receiver<()>), String>;
+
+/// Starts every queued item the concurrency limits allow.
+fn pump_queue(app_handle: &tauri::AppHandle, custom_path: Option<String>) {
+    let queue = app_handle.state::<TransferQueueState>();
+    let transfers = app_handle.state::<TransferState>();
+    let started: Vec<(QueueItem, QueueRegistration)> = {
+        let mut queue = queue.queue.lock().unwrap();
+        // Register the transfers before letting go of the queue, so a running
+        // item can always be cancelled through its transfer
+        queue
+            .start_ready()
+            .into_iter()
+            .map(|item| {
+                let registration = transfers.transfers.lock().unwrap().register(Some(item.id.clone()));
+                (item, registration.map(|(_, generation, cancel)| (generation, cancel)))
+            })
+            .collect()
+    };
+
+    for (item, registration) in started {
+        let _ = app_handle.emit("transfer-queue", &item);
+        tokio::spawn(run_queue_item(app_handle.clone(), item, registration, custom_path.clone()));
+    }
+}
+
+async fn run_queue_item(app_handle: tauri::AppHandle, item: QueueItem, registration: QueueRegistration, custom_path: Option<String>) {
+    let adb_path = get_binary_path("adb", custom_path.clone());
+
+    let transfers = app_handle.state::<TransferState>();
+    let result = match registration {
+        Ok((generation, mut cancel)) => {
+            let _transfer = TransferGuard { state: &transfers, id: item.id.clone(), generation };
+            push_with_retries(&app_handle, &item, &adb_path, &mut cancel).await
+        }
+        Err(e) => Err(e),
+    };
+
+    let cancelled = result.as_ref().is_err_and(|e| e == TRANSFER_CANCELLED);
+    let finished = app_handle.state::<TransferQueueState>().queue.lock().unwrap().finish(&item.id, result, cancelled);
+    if let Some(finished) = finished {
+        let _ = app_handle.emit("transfer-queue", finished);
+    }
+    pump_queue(&app_handle, custom_path);
+}
+
+/// Pushes a queue item, retrying transient failures with a growing delay.
+/// Retries show up as `transfer-queue` events, only the last attempt emits a
+/// final `transfer-progress` event.
+async fn push_with_retries(
+    app_handle: &tauri::AppHandle,
+    item: &QueueItem,
+    adb_path: &str,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<(String, Option<u64>), String> {
+    // The trailing slash makes the push land inside the directory
+    let remote_dir = format!("{}/", item.remote_dir.trim_end_matches('/'));
+    let total = std::fs::metadata(&item.local_path).ok().map(|m| m.len());
+    let mut attempt = 1;
+
+    loop {
+        let mut tracker = ProgressTracker::new(&item.id, TransferKind::Push, &item.local_path, total);
+        let result = push_one(app_handle, &item.device, &item.local_path, &remote_dir, adb_path, &mut tracker, cancel).await;
+        let error = result.as_ref().err().cloned();
+        if let Some(updated) = app_handle.state::<TransferQueueState>().queue.lock().unwrap().record_attempt(&item.id, error.clone()) {
+            let _ = app_handle.emit("transfer-queue", updated);
+        }
+
+        match error {
+            Some(e) if e != TRANSFER_CANCELLED && attempt < MAX_ATTEMPTS && transfer_queue::is_transient(&e) => {
+                let delay = Duration::from_secs(attempt as u64);
+                if cancellable(cancel, tokio::time::sleep(delay)).await.is_none() {
+                    let cancelled = Err(TRANSFER_CANCELLED.to_string());
+                    finish_push(app_handle, &tracker, &cancelled);
+                    return cancelled;
+                }
+                attempt += 1;
+            }
+            _ => {
+                finish_push(app_handle, &tracker, &result);
+                return result;
+            }
+        }
+    }
+}
+
+const TRANSFER_CANCELLED: &str = "Transfer cancelled";
+
+/// Emits the final progress event for a pull and passes its result through.
+fn finish_pull(window: &Window, tracker: &ProgressTracker, result: Result<TransferSummary, String>) -> Result<TransferSummary, String> {
+    let progress = match &result {
+        Ok(_) => tracker.snapshot(TransferStatus::Done, None),
+        Err(e) if e == TRANSFER_CANCELLED => tracker.snapshot(TransferStatus::Cancelled, Some(e.clone())),
+        Err(e) => tracker.snapshot(TransferStatus::Failed, Some(e.clone())),
+    };
+    emit_progress(window, progress);
+    result
+}
+
+/// Runs `adb pull`, for when the adb server can't be reached directly.
+async fn cli_pull(adb_path: &str, device: &str, remote_path: &str, local: &Path, cancel: &mut oneshot::Receiver<()>) -> Result<TransferSummary, String> {
+    let mut command = create_command(adb_path);
+    command.arg("-s").arg(device).arg("pull").arg(remote_path).arg(local).kill_on_drop(true);
+
+    let output = cancellable(cancel, command.output())
+        .await
+        .ok_or_else(|| TRANSFER_CANCELLED.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(transfer::parse_cli_summary(&String::from_utf8_lossy(&output.stdout)).unwrap_or_default())
+}
+
+/// `adb push` for when the server can't be reached directly. The CLI only
+/// reports the byte count when it isn't attached to a terminal, if at all.
+async fn cli_push(adb_path: &str, device: &str, local: &Path, remote_path: &str, cancel: &mut oneshot::Receiver<()>) -> Result<Option<u64>, String> {
+    let mut command = create_command(adb_path);
+    command.arg("-s").arg(device).arg("push").arg(local).arg(remote_path).kill_on_drop(true);
+
+    let output = cancellable(cancel, command.output())
+        .await
+        .ok_or_else(|| TRANSFER_CANCELLED.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(transfer::parse_cli_summary(&String::from_utf8_lossy(&output.stdout)).map(|s| s.bytes))
+}
+
+/// Copies a file from the device. `local_path` may be a directory, in which
+/// case the file keeps its name.
+#[tauri::command]
+pub async fn pull_file(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    remote_path: String,
+    local_path: String,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<TransferSummary, String> {
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let local = transfer::pull_destination(&remote_path, Path::new(&local_path));
+    if let Some(parent) = local.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let client = AdbClient::default();
+    let total = client.stat(&device, &remote_path).await.ok().map(|s| s.size);
+    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Pull, &remote_path, total);
+
+    let native = cancellable(&mut cancel, client.pull(&device, &remote_path, &local, &mut |n| {
+        if let Some(progress) = tracker.advance(n) { emit_progress(&window, progress); }
+    })).await;
+
+    let result = match native {
+        Some(Ok(bytes)) => Ok(TransferSummary { files: 1, bytes, failures: Vec::new() }),
+        Some(Err(AdbError::Failed(e))) => Err(format!("Failed to pull {}: {}", remote_path, e)),
+        Some(Err(AdbError::Unavailable(_))) => cli_pull(&get_binary_path("adb", custom_path), &device, &remote_path, &local, &mut cancel).await,
+        None => {
+            // The aborted pull didn't get to clean up after itself
+            let _ = std::fs::remove_file(&local);
+            Err(TRANSFER_CANCELLED.to_string())
+        }
+    };
+    finish_pull(&window, &tracker, result)
+}
+
+/// Recursively copies a directory from the device, like `adb pull`. Files that
+/// fail are listed in the summary instead of aborting the whole transfer.
+/// Files already copied are kept when the transfer is cancelled.
+#[tauri::command]
+pub async fn pull_dir(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    remote_path: String,
+    local_path: String,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<TransferSummary, String> {
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let local = transfer::pull_destination(&remote_path, Path::new(&local_path));
+    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Pull, &remote_path, None);
+
+    let native = cancellable(&mut cancel, transfer::pull_dir(&AdbClient::default(), &device, &remote_path, &local, &mut |file, n| {
+        tracker.set_file(file);
+        if let Some(progress) = tracker.advance(n) { emit_progress(&window, progress); }
+    })).await;
+
+    let result = match native {
+        Some(Ok(summary)) => Ok(summary),
+        Some(Err(AdbError::Failed(e))) => Err(format!("Failed to pull {}: {}", remote_path, e)),
+        // adb pull picks the destination the same way, so hand it the original path
+        Some(Err(AdbError::Unavailable(_))) => {
+            cli_pull(&get_binary_path("adb", custom_path), &device, &remote_path, Path::new(&local_path), &mut cancel).await
+        }
+        None => Err(TRANSFER_CANCELLED.to_string()),
+    };
+    finish_pull(&window, &tracker, result)
+}
+
+fn check_remote_path(path: &str) -> Result<&str, String> {
+    let path = path.trim();
+    if !path.starts_with('/') {
+        return Err(format!("Remote paths must be absolute: {}", path));
+    }
+    Ok(path)
+}
+
+/// Lists a device directory for the file browser, directories first.
+#[tauri::command]
+pub async fn list_remote_dir(device: String, path: String, custom_path: Option<String>) -> Result<Vec<RemoteEntry>, String> {
     let adb_path = get_binary_path("adb", custom_path);
-    
-    let output = create_command(&adb_path)
-        .arg("-s")
-        .arg(&device)
-        .arg("install")
-        .arg(&file_path)
-        .output()
-        .await;
+    let path = check_remote_path(&path)?;
+    // The trailing slash makes ls list a symlinked directory's contents rather than the link
+    let ls_command = format!("ls -la {}", shell_quote(&format!("{}/", path.trim_end_matches('/'))));
+
+    let mut entries = match AdbClient::default().list_dir(&device, path).await {
+        Ok(listing) => {
+            let mut entries: Vec<RemoteEntry> = listing.into_iter().map(|e| RemoteEntry::from_sync(path, e)).collect();
+
+            // LIST doesn't carry link targets, ls does
+            if entries.iter().any(|e| e.is_symlink) {
+                if let Ok(output) = shell_output(&adb_path, &device, &ls_command).await {
+                    let detailed = remote_files::parse_ls(path, &output);
+                    for entry in entries.iter_mut().filter(|e| e.is_symlink) {
+                        entry.symlink_target = detailed.iter().find(|d| d.name == entry.name).and_then(|d| d.symlink_target.clone());
+                    }
+                }
+            }
+            entries
+        }
+        Err(AdbError::Failed(e)) => return Err(e),
+        Err(AdbError::Unavailable(_)) => remote_files::parse_ls(path, &shell_output(&adb_path, &device, &ls_command).await?),
+    };
 
-     match output {
-        Ok(o) => {
-             let out_text = String::from_utf8_lossy(&o.stdout);
-             let err_text = String::from_utf8_lossy(&o.stderr);
-             
-             if o.status.success() {
-                 json!({ "success": true, "message": out_text.trim() })
-             } else {
-                 json!({ "success": false, "message": err_text.trim() })
-             }
-        },
-        Err(e) => json!({ "success": false, "message": e.to_string() })
+    remote_files::sort_entries(&mut entries);
+    Ok(entries)
+}
+
+/// Runs a device shell command for its exit status, like rm, mkdir, mv or
+/// am force-stop. What it printed becomes the error message.
+async fn shell_command_checked(adb_path: &str, device: &str, command: &str) -> Result<(), String> {
+    shell_output(adb_path, device, &format!("({}) 2>&1", command)).await.map(|_| ())
+}
+
+#[tauri::command]
+pub async fn delete_remote(device: String, path: String, recursive: Option<bool>, custom_path: Option<String>) -> Result<(), String> {
+    let path = check_remote_path(&path)?;
+    if path.trim_end_matches('/').is_empty() {
+        return Err("Refusing to delete /".to_string());
     }
+    let flags = if recursive.unwrap_or(false) { "-rf" } else { "-f" };
+    shell_command_checked(&get_binary_path("adb", custom_path), &device, &format!("rm {} {}", flags, shell_quote(path))).await
+}
+
+#[tauri::command]
+pub async fn mkdir_remote(device: String, path: String, parents: Option<bool>, custom_path: Option<String>) -> Result<(), String> {
+    let path = check_remote_path(&path)?;
+    let flags = if parents.unwrap_or(true) { "-p " } else { "" };
+    shell_command_checked(&get_binary_path("adb", custom_path), &device, &format!("mkdir {}{}", flags, shell_quote(path))).await
 }
 
+/// Renames or moves a device path, refusing to overwrite an existing destination.
 #[tauri::command]
-pub async fn kill_adb(window: Window, custom_path: Option<String>) -> Result<serde_json::Value, String> {
+pub async fn rename_remote(device: String, from: String, to: String, custom_path: Option<String>) -> Result<(), String> {
+    let (from, to) = (shell_quote(check_remote_path(&from)?), shell_quote(check_remote_path(&to)?));
+    let command = format!("if [ -e {to} ]; then echo 'Destination already exists'; exit 1; else mv {from} {to}; fi");
+    shell_command_checked(&get_binary_path("adb", custom_path), &device, &command).await
+}
+
+/// Files hashed per `sha256sum` call, to keep the command line short.
+const HASH_BATCH: usize = 64;
+
+/// Scans the device side of a sync, starting the adb server through the CLI if it isn't running.
+async fn scan_remote_tree(adb_path: &str, device: &str, remote_dir: &str) -> Result<dir_sync::Tree, String> {
+    let client = AdbClient::default();
+    match dir_sync::scan_remote(&client, device, remote_dir).await {
+        Err(AdbError::Unavailable(_)) => {
+            let _ = create_command(adb_path).arg("start-server").output().await;
+            dir_sync::scan_remote(&client, device, remote_dir).await.map_err(|e| e.to_string())
+        }
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+/// Files whose local and device SHA-256 differ. Files the device couldn't hash count as different.
+async fn hash_mismatches(
+    adb_path: &str,
+    device: &str,
+    local_dir: &Path,
+    remote_dir: &str,
+    candidates: &[String],
+) -> Result<BTreeSet<String>, String> {
+    let mut mismatches = BTreeSet::new();
+    for batch in candidates.chunks(HASH_BATCH) {
+        let files: Vec<String> = batch.iter().map(|f| shell_quote(f)).collect();
+        let command = format!("cd {} && sha256sum -- {} 2>/dev/null; true", shell_quote(remote_dir), files.join(" "));
+        let remote = dir_sync::parse_sha256sum(&shell_output(adb_path, device, &command).await?);
+
+        // Hashing reads whole files, keep it off the async runtime
+        let files = batch.to_vec();
+        let root = local_dir.to_path_buf();
+        let local = tokio::task::spawn_blocking(move || {
+            files
+                .into_iter()
+                .map(|file| match dir_sync::sha256_file(&root.join(&file)) {
+                    Ok(hash) => Ok((file, hash)),
+                    Err(e) => Err(format!("Failed to hash {}: {}", file, e)),
+                })
+                .collect::<Result<Vec<_>, String>>()
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)?;
+
+        for (file, hash) in local {
+            if remote.get(&file) != Some(&hash) {
+                mismatches.insert(file);
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Makes `remote_dir` match `local_dir`: pushes new and changed files and, with
+/// `delete_extras`, removes what only exists on the device. A dry run only
+/// returns the plan. Pushes report `transfer-progress` events under one
+/// transfer id and can be aborted with `cancel_transfer`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sync_dir(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    local_dir: String,
+    remote_dir: String,
+    compare: Option<CompareMode>,
+    delete_extras: Option<bool>,
+    dry_run: Option<bool>,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<SyncReport, String> {
     let adb_path = get_binary_path("adb", custom_path);
-    let _ = window.emit("scrcpy-log", "[SYSTEM] Terminating ADB stack...".to_string());
-    
-    let mut child = create_command(&adb_path)
-        .arg("kill-server")
-        .spawn()
-        .map_err(|e| e.to_string())?;
-        
-    let _ = child.wait().await;
+    let remote_dir = check_remote_path(&remote_dir)?.trim_end_matches('/').to_string();
+    if remote_dir.is_empty() {
+        return Err("Refusing to sync into /".to_string());
+    }
+    let local_root = Path::new(&local_dir);
+    if !local_root.is_dir() {
+        return Err(format!("Not a directory: {}", local_dir));
+    }
+    let mode = compare.unwrap_or_default();
+    let dry_run = dry_run.unwrap_or(false);
+
+    let local = dir_sync::scan_local(local_root).map_err(|e| format!("Failed to read {}: {}", local_dir, e))?;
+    let remote = scan_remote_tree(&adb_path, &device, &remote_dir).await?;
+    let mismatches = match mode {
+        CompareMode::Hash => hash_mismatches(&adb_path, &device, local_root, &remote_dir, &dir_sync::hash_candidates(&local, &remote)).await?,
+        CompareMode::SizeMtime => BTreeSet::new(),
+    };
+    let plan = dir_sync::plan(&local, &remote, mode, &mismatches, delete_extras.unwrap_or(false));
 
-    // Force kill adb process via OS specifics
-    #[cfg(target_os = "windows")]
-    {
-        let _ = TokioCommand::new("taskkill")
-            .args(&["/F", "/IM", "adb.exe", "/T"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .await;
+    if dry_run {
+        return Ok(SyncReport { plan, dry_run, summary: None });
     }
 
-    #[cfg(not(target_os = "windows"))]
-    {
-         let _ = TokioCommand::new("pkill")
-            .arg("adb")
-            .output()
-            .await;
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Push, &local_dir, Some(plan.bytes_to_push));
+    let mut summary = TransferSummary::default();
+    let client = AdbClient::default();
+    let mut cancelled = false;
+
+    for action in &plan.actions {
+        match action {
+            SyncAction::Push { path, size, .. } => {
+                let local_file = local_root.join(path);
+                tracker.set_file(&local_file.to_string_lossy());
+                // adbd creates missing parent directories on its own, and so does adb push
+                let remote = adb::remote_join(&remote_dir, path);
+                let native = cancellable(&mut cancel, client.push(&device, &local_file, &remote, &mut |n| {
+                    if let Some(progress) = tracker.advance(n) { emit_progress(&window, progress); }
+                })).await;
+
+                let pushed = match native {
+                    Some(Err(AdbError::Unavailable(_))) => match cli_push(&adb_path, &device, &local_file, &remote, &mut cancel).await {
+                        Ok(bytes) => {
+                            if let Some(progress) = tracker.advance(*size) { emit_progress(&window, progress); }
+                            Some(Ok(bytes.unwrap_or(*size)))
+                        }
+                        Err(e) if e == TRANSFER_CANCELLED => None,
+                        Err(e) => Some(Err(AdbError::Failed(e))),
+                    },
+                    other => other,
+                };
+
+                match pushed {
+                    Some(result) => summary.record(path, result),
+                    None => {
+                        cancelled = true;
+                        break;
+                    }
+                }
+            }
+            SyncAction::Delete { path, .. } => {
+                let command = format!("rm -rf {}", shell_quote(&adb::remote_join(&remote_dir, path)));
+                match cancellable(&mut cancel, shell_command_checked(&adb_path, &device, &command)).await {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => summary.fail(path, e),
+                    None => {
+                        cancelled = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let progress = if cancelled {
+        tracker.snapshot(TransferStatus::Cancelled, Some(TRANSFER_CANCELLED.to_string()))
+    } else if summary.failures.is_empty() {
+        tracker.snapshot(TransferStatus::Done, None)
+    } else {
+        tracker.snapshot(TransferStatus::Failed, Some(format!("{} of {} actions failed", summary.failures.len(), plan.actions.len())))
+    };
+    emit_progress(&window, progress);
+
+    if cancelled {
+        return Err(TRANSFER_CANCELLED.to_string());
+    }
+    Ok(SyncReport { plan, dry_run, summary: Some(summary) })
+}
+
+/// Where `native_install` stages the APK of a transfer. Named after the
+/// transfer id so concurrent installs don't overwrite each other.
+fn staged_apk_path(transfer_id: &str) -> String {
+    let id: String = transfer_id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    adb::remote_join(REMOTE_TMP_DIR, &format!("scrcpy_gui_install_{}.apk", id))
+}
+
+/// Stages the APK at `staged` through the sync service, so its upload can
+/// report progress, then installs it with `pm install`. Returns pm's output.
+/// The caller removes the staged file, this may be dropped halfway through.
+async fn native_install(device: &str, local: &Path, staged: &str, flags: &[String], progress: &mut (dyn FnMut(u64) + Send)) -> Result<String, AdbError> {
+    let client = AdbClient::default();
+    client.push(device, local, staged, progress).await?;
+
+    let mut command = String::from("pm install");
+    for arg in flags.iter().map(String::as_str).chain(std::iter::once(staged)) {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    // pm's output says why an install failed, the caller reads it whatever the exit status
+    client.shell(device, &command).await.map(|shell| shell.output)
+}
+
+/// Runs `adb install` or `adb install-multiple`. Failures come back with
+/// whatever adb printed, which carries the `INSTALL_FAILED_*` code.
+async fn cli_install(
+    adb_path: &str,
+    device: &str,
+    subcommand: &str,
+    flags: &[String],
+    files: &[PathBuf],
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<String, String> {
+    let mut command = create_command(adb_path);
+    command.arg("-s").arg(device).arg(subcommand).args(flags).args(files).kill_on_drop(true);
+
+    match cancellable(cancel, command.output()).await {
+        Some(Ok(o)) if o.status.success() => Ok(String::from_utf8_lossy(&o.stdout).trim().to_string()),
+        Some(Ok(o)) => {
+            let stderr = String::from_utf8_lossy(&o.stderr).trim().to_string();
+            Err(if stderr.is_empty() { String::from_utf8_lossy(&o.stdout).trim().to_string() } else { stderr })
+        }
+        Some(Err(e)) => Err(e.to_string()),
+        None => Err(TRANSFER_CANCELLED.to_string()),
+    }
+}
+
+/// Reads what split selection needs from the device.
+async fn device_spec(adb_path: &str, device: &str) -> Result<DeviceSpec, String> {
+    let (props, density) = tokio::join!(shell_output(adb_path, device, "getprop"), shell_output(adb_path, device, "wm density"));
+    Ok(DeviceSpec::from_props(&parse_getprop(&props?), density.ok().and_then(|d| parse_wm_density(&d))))
+}
+
+/// Installs the splits picked for the device with `adb install-multiple`, then
+/// pushes any OBB files. Returns the install output.
+async fn install_bundle(
+    window: &Window,
+    adb_path: &str,
+    device: &str,
+    bundle: &PreparedBundle,
+    options: &InstallOptions,
+    tracker: &mut ProgressTracker,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<String, String> {
+    let output = cli_install(adb_path, device, "install-multiple", &options.adb_flags(), &bundle.apks, cancel).await?;
+    let apk_bytes: u64 = bundle.apks.iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum();
+    if let Some(progress) = tracker.advance(apk_bytes) { emit_progress(window, progress); }
+
+    let client = AdbClient::default();
+    for (local, remote) in &bundle.obbs {
+        tracker.set_file(&local.to_string_lossy());
+        let pushed = cancellable(cancel, client.push(device, local, remote, &mut |n| {
+            if let Some(progress) = tracker.advance(n) { emit_progress(window, progress); }
+        })).await;
+
+        match pushed {
+            Some(Ok(_)) => {}
+            Some(Err(AdbError::Failed(e))) => return Err(format!("Installed, but failed to push {}: {}", remote, e)),
+            Some(Err(AdbError::Unavailable(_))) => {
+                let mut command = create_command(adb_path);
+                command.arg("-s").arg(device).arg("push").arg(local).arg(remote).kill_on_drop(true);
+                match cancellable(cancel, command.output()).await {
+                    Some(Ok(o)) if o.status.success() => {}
+                    Some(Ok(o)) => return Err(format!("Installed, but failed to push {}: {}", remote, String::from_utf8_lossy(&o.stderr).trim())),
+                    Some(Err(e)) => return Err(e.to_string()),
+                    None => return Err(TRANSFER_CANCELLED.to_string()),
+                }
+            }
+            None => return Err(TRANSFER_CANCELLED.to_string()),
+        }
+    }
+    Ok(output)
+}
+
+/// Installs an APK, or a split set: an `.apks`/`.xapk` archive or a folder of
+/// split APKs. Only the splits matching the device ABI, density and language
+/// are installed, and XAPK expansion files are copied to their OBB folder.
+/// Failures carry the parsed `INSTALL_FAILED_*` code with an explanation and fix.
+#[tauri::command]
+pub async fn install_apk(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    file_path: String,
+    options: Option<InstallOptions>,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let options = options.unwrap_or_default();
+    options.validate()?;
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let adb_path = get_binary_path("adb", custom_path);
+    let local = Path::new(&file_path);
+    let mut selection = None;
+
+    let (tracker, result) = match apk_bundle::bundle_kind(local) {
+        Some(kind) => {
+            let prepared = match device_spec(&adb_path, &device).await {
+                Ok(spec) => {
+                    let source = local.to_path_buf();
+                    let workdir = std::env::temp_dir().join(format!("scrcpy_gui_install_{}", transfer.id));
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let flag = stop.clone();
+                    let mut extraction = tokio::task::spawn_blocking(move || apk_bundle::prepare(&source, kind, &spec, &workdir, &flag));
+
+                    match cancellable(&mut cancel, &mut extraction).await {
+                        Some(joined) => joined.map_err(|e| e.to_string()).and_then(|r| r),
+                        None => {
+                            // Let it clean up its workdir before reporting the cancel
+                            stop.store(true, Ordering::Relaxed);
+                            let _ = extraction.await;
+                            Err(TRANSFER_CANCELLED.to_string())
+                        }
+                    }
+                }
+                Err(e) => Err(format!("Failed to read device configuration: {}", e)),
+            };
+
+            match prepared {
+                Ok(bundle) => {
+                    let _ = window.emit("scrcpy-log", format!("[SYSTEM] Installing {} of {} splits: {}",
+                        bundle.selection.selected.len(),
+                        bundle.selection.selected.len() + bundle.selection.skipped.len(),
+                        bundle.selection.selected.join(", ")));
+                    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Install, &file_path, Some(bundle.total_size()));
+                    let result = install_bundle(&window, &adb_path, &device, &bundle, &options, &mut tracker, &mut cancel).await;
+                    selection = Some(bundle.selection.clone());
+                    (tracker, result)
+                }
+                Err(e) => (ProgressTracker::new(&transfer.id, TransferKind::Install, &file_path, None), Err(e)),
+            }
+        }
+        None => {
+            let total = std::fs::metadata(local).ok().map(|m| m.len());
+            let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Install, &file_path, total);
+
+            let files = [local.to_path_buf()];
+
+            // Only the adb client can stream an incremental install
+            let result = if options.incremental {
+                cli_install(&adb_path, &device, "install", &options.adb_flags(), &files, &mut cancel).await
+            } else {
+                let staged = staged_apk_path(&transfer.id);
+                let native = cancellable(&mut cancel, native_install(&device, local, &staged, &options.pm_flags(), &mut |n| {
+                    if let Some(progress) = tracker.advance(n) { emit_progress(&window, progress); }
+                })).await;
+                // Also after a cancel, which leaves a partial or uninstalled APK behind
+                let _ = AdbClient::default().shell(&device, &format!("rm -f {}", shell_quote(&staged))).await;
+
+                match native {
+                    Some(Ok(output)) if output.contains("Success") => Ok(output.trim().to_string()),
+                    Some(Ok(output)) => Err(output.trim().to_string()),
+                    Some(Err(AdbError::Failed(e))) => Err(e),
+                    Some(Err(AdbError::Unavailable(_))) => cli_install(&adb_path, &device, "install", &options.adb_flags(), &files, &mut cancel).await,
+                    None => Err(TRANSFER_CANCELLED.to_string()),
+                }
+            };
+            (tracker, result)
+        }
+    };
+
+    Ok(match result {
+        Ok(message) => {
+            emit_progress(&window, tracker.snapshot(TransferStatus::Done, None));
+            json!({ "success": true, "message": message, "transferId": transfer.id, "splits": selection })
+        }
+        Err(e) => {
+            let status = if e == TRANSFER_CANCELLED { TransferStatus::Cancelled } else { TransferStatus::Failed };
+            emit_progress(&window, tracker.snapshot(status, Some(e.clone())));
+            let error = InstallFailure::from_output(&e).map(InstallError::from);
+            json!({ "success": false, "message": e, "error": error, "transferId": transfer.id, "splits": selection })
+        }
+    })
+}
+
+/// Lists installed packages with their APK path and version. `filter` narrows
+/// it down like `pm list packages -3/-s/-d/-e`.
+#[tauri::command]
+pub async fn list_packages(device: String, filter: Option<PackageFilter>, custom_path: Option<String>) -> Result<Vec<PackageInfo>, String> {
+    let adb_path = get_binary_path("adb", custom_path);
+    let flag = filter.unwrap_or_default().pm_flag().unwrap_or("");
+    let list_command = format!("pm list packages -f --show-versioncode {} 2>&1", flag);
+
+    let (listing, system, disabled, dumpsys) = tokio::join!(
+        shell_output(&adb_path, &device, &list_command),
+        shell_output(&adb_path, &device, "pm list packages -s"),
+        shell_output(&adb_path, &device, "pm list packages -d"),
+        shell_output(&adb_path, &device, "dumpsys package packages"),
+    );
+
+    // An unknown option makes pm fail, so only a failure on the retry counts
+    let mut listing = listing.unwrap_or_else(|e| e);
+    if !listing.contains("package:") {
+        // --show-versioncode only exists since Android 9
+        listing = shell_output(&adb_path, &device, &format!("pm list packages -f {} 2>&1", flag)).await?;
+        if !listing.contains("package:") && !listing.trim().is_empty() {
+            return Err(listing.trim().to_string());
+        }
     }
 
-    let _ = window.emit("scrcpy-log", "[SYSTEM] ADB Stack Terminated.".to_string());
-    Ok(json!({ "success": true, "message": "ADB Stack Terminated" }))
+    Ok(packages::build_package_list(
+        &listing,
+        &packages::parse_package_names(&system.unwrap_or_default()),
+        &packages::parse_package_names(&disabled.unwrap_or_default()),
+        &packages::parse_version_names(&dumpsys.unwrap_or_default()),
+    ))
+}
+
+/// Uninstalls a package, keeping its data and cache directories with `keep_data`.
+#[tauri::command]
+pub async fn uninstall_package(device: String, package: String, keep_data: Option<bool>, custom_path: Option<String>) -> Result<(), String> {
+    let package = check_package_name(&package)?;
+    let flags = if keep_data.unwrap_or(false) { "-k " } else { "" };
+    let output = shell_output(&get_binary_path("adb", custom_path), &device, &format!("pm uninstall {}{} 2>&1", flags, package)).await?;
+    packages::pm_result(&output)
+}
+
+#[tauri::command]
+pub async fn clear_app_data(device: String, package: String, custom_path: Option<String>) -> Result<(), String> {
+    let package = check_package_name(&package)?;
+    let output = shell_output(&get_binary_path("adb", custom_path), &device, &format!("pm clear {} 2>&1", package)).await?;
+    packages::pm_result(&output)
+}
+
+#[tauri::command]
+pub async fn force_stop_app(device: String, package: String, custom_path: Option<String>) -> Result<(), String> {
+    let package = check_package_name(&package)?;
+    shell_command_checked(&get_binary_path("adb", custom_path), &device, &format!("am force-stop {}", package)).await
+}
+
+/// Enables or disables a package for the current user. Disabling works on
+/// system apps too, without root, through `pm disable-user`.
+#[tauri::command]
+pub async fn set_package_enabled(device: String, package: String, enabled: bool, custom_path: Option<String>) -> Result<(), String> {
+    let package = check_package_name(&package)?;
+    let command = if enabled { format!("pm enable {} 2>&1", package) } else { format!("pm disable-user {} 2>&1", package) };
+    let output = shell_output(&get_binary_path("adb", custom_path), &device, &command).await?;
+    // pm prints "Package <name> new state: enabled" on success
+    if output.contains("new state") { Ok(()) } else { Err(output.trim().to_string()) }
+}
+
+/// Starts a package's launcher activity and returns its component name.
+#[tauri::command]
+pub async fn launch_app(device: String, package: String, custom_path: Option<String>) -> Result<String, String> {
+    let adb_path = get_binary_path("adb", custom_path);
+    let package = check_package_name(&package)?;
+
+    let resolve = format!("cmd package resolve-activity --brief -a android.intent.action.MAIN -c android.intent.category.LAUNCHER {} 2>&1", package);
+    let component = packages::parse_resolved_activity(&shell_output(&adb_path, &device, &resolve).await?)
+        .ok_or_else(|| format!("{} has no launcher activity", package))?;
+
+    let output = shell_output(&adb_path, &device, &format!("am start -n {} 2>&1", shell_quote(&component))).await?;
+    if output.lines().any(|l| l.starts_with("Error")) {
+        return Err(output.trim().to_string());
+    }
+    Ok(component)
+}
+
+/// Copies an installed app's APKs (base and splits) from the device into
+/// `local_dir/<package>/`. With `bundle` they're packed into
+/// `local_dir/<package>.apks` instead, which `install_apk` accepts. Reports
+/// `transfer-progress` events and can be aborted with `cancel_transfer`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn backup_apk(
+    window: Window,
+    transfers: State<'_, TransferState>,
+    device: String,
+    package: String,
+    local_dir: String,
+    bundle: Option<bool>,
+    transfer_id: Option<String>,
+    custom_path: Option<String>,
+) -> Result<ApkBackup, String> {
+    let adb_path = get_binary_path("adb", custom_path);
+    let package = check_package_name(&package)?.to_string();
+    let remote_paths = packages::parse_pm_path(&shell_output(&adb_path, &device, &format!("pm path {} 2>&1", package)).await?);
+    if remote_paths.is_empty() {
+        return Err(format!("{} is not installed", package));
+    }
+
+    let dest = Path::new(&local_dir).join(&package);
+    std::fs::create_dir_all(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+
+    let (transfer, mut cancel) = begin_transfer(&transfers, transfer_id)?;
+    let client = AdbClient::default();
+    let mut total = 0;
+    for remote in &remote_paths {
+        total += client.stat(&device, remote).await.map(|s| s.size).unwrap_or(0);
+    }
+    let mut tracker = ProgressTracker::new(&transfer.id, TransferKind::Pull, &package, Some(total).filter(|t| *t > 0));
+
+    let mut files = Vec::new();
+    let mut bytes = 0;
+    let mut result = Ok(());
+    for remote in &remote_paths {
+        let local = dest.join(transfer::remote_basename(remote));
+        tracker.set_file(remote);
+
+        let native = cancellable(&mut cancel, client.pull(&device, remote, &local, &mut |n| {
+            if let Some(progress) = tracker.advance(n) { emit_progress(&window, progress); }
+        })).await;
+
+        let pulled = match native {
+            Some(Ok(n)) => Ok(n),
+            Some(Err(AdbError::Failed(e))) => Err(format!("Failed to pull {}: {}", remote, e)),
+            Some(Err(AdbError::Unavailable(_))) => cli_pull(&adb_path, &device, remote, &local, &mut cancel).await.map(|s| s.bytes),
+            None => {
+                let _ = std::fs::remove_file(&local);
+                Err(TRANSFER_CANCELLED.to_string())
+            }
+        };
+        match pulled {
+            Ok(n) => {
+                bytes += n;
+                files.push(local);
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    let mut backup = ApkBackup { package: package.clone(), files: Vec::new(), bytes, bundle: None };
+    if result.is_ok() && bundle.unwrap_or(false) {
+        let archive = Path::new(&local_dir).join(format!("{}.apks", package));
+        let apks = files.clone();
+        let target = archive.clone();
+        result = tokio::task::spawn_blocking(move || apk_bundle::write_apks(&apks, &target))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+        if result.is_ok() {
+            let _ = std::fs::remove_dir_all(&dest);
+            files.clear();
+            backup.bundle = Some(archive.to_string_lossy().to_string());
+        }
+    }
+    backup.files = files.iter().map(|f| f.to_string_lossy().to_string()).collect();
+
+    let summary = finish_pull(&window, &tracker, result.map(|_| TransferSummary::default()));
+    summary.map(|_| backup)
+}
+
+/// Shuts down our adb server and kills whatever is left of it. Only processes
+/// of the adb binary we resolve, on our server port, are touched unless `all` is
+/// set, which kills every adb on the machine.
+#[tauri::command]
+pub async fn kill_adb(window: Window, custom_path: Option<String>, all: Option<bool>) -> Result<serde_json::Value, String> {
+    let adb_path = get_binary_path("adb", custom_path);
+    let all = all.unwrap_or(false);
+    let _ = window.emit("scrcpy-log", format!("[SYSTEM] Terminating {}...", if all { "all ADB processes" } else { "ADB stack" }));
+    
+    let _ = create_command(&adb_path).arg("kill-server").output().await;
+
+    let running = processes::list_adb_processes().await;
+    let targets = if all {
+        running.iter().map(|p| p.pid).collect()
+    } else {
+        match processes::resolve_executable(&adb_path) {
+            Some(exe) => processes::select_own(&running, &exe, adb::server().port),
+            None => Vec::new(),
+        }
+    };
+
+    let mut pids = Vec::new();
+    for pid in targets {
+        if processes::kill_pid(pid).await {
+            pids.push(pid);
+        }
+    }
+
+    let message = if pids.is_empty() {
+        "ADB Stack Terminated".to_string()
+    } else {
+        format!("ADB Stack Terminated, killed pids {:?}", pids)
+    };
+    let _ = window.emit("scrcpy-log", format!("[SYSTEM] {}.", message));
+    Ok(json!({ "success": true, "message": message, "pids": pids }))
 }
 
 #[tauri::command]
@@ -448,164 +1644,142 @@ pub async fn list_scrcpy_options(device: String, arg: String, custom_path: Optio
     }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ScrcpyConfig {
-    device: String,
-    session_mode: String,
-    // ... other fields matching JS ...
-    bitrate: Option<u32>,
-    fps: Option<u32>,
-    stay_awake: Option<bool>,
-    turn_off: Option<bool>,
-    audio_enabled: Option<bool>,
-    always_on_top: Option<bool>,
-    fullscreen: Option<bool>,
-    borderless: Option<bool>,
-    record: Option<bool>,
-    record_path: Option<String>,
-    scrcpy_path: Option<String>,
-    otg_enabled: Option<bool>,
-    otg_pure: Option<bool>,
-    camera_facing: Option<String>,
-    camera_id: Option<String>,
-    codec: Option<String>,
-    camera_ar: Option<String>,
-    camera_high_speed: Option<bool>,
-    vd_width: Option<u32>,
-    vd_height: Option<u32>,
-    vd_dpi: Option<u32>,
-    rotation: Option<String>,
-    res: Option<String>,
-}
-
-fn build_scrcpy_args(config: &ScrcpyConfig, video_dir_fallback: Option<String>) -> Vec<String> {
-    let mut args = Vec::new();
-    
-    // Construct arguments based on config
-    if !config.device.is_empty() {
-        args.push("-s".to_string());
-        args.push(config.device.clone());
+/// Runs `scrcpy --list-*` and returns stdout and stderr combined, since scrcpy prints the lists as log lines.
+async fn scrcpy_list_output(device: &str, arg: &str, custom_path: Option<String>) -> Result<String, String> {
+    let exe_path = get_binary_path("scrcpy", custom_path);
+
+    let output = create_command(&exe_path)
+        .arg("-s")
+        .arg(device)
+        .arg(arg)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let combined = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    if output.status.success() {
+        Ok(combined)
+    } else {
+        Err(combined.trim().to_string())
     }
+}
 
-    let codec = config.codec.as_deref().unwrap_or("h264");
-    args.push(format!("--video-codec={}", codec));
+#[tauri::command]
+pub async fn list_encoders(device: String, custom_path: Option<String>) -> Result<Vec<Encoder>, String> {
+    let output = scrcpy_list_output(&device, "--list-encoders", custom_path).await?;
+    Ok(scrcpy_options::parse_encoders(&output))
+}
 
-    let otg_enabled = config.otg_enabled.unwrap_or(false);
-    let otg_pure = config.otg_pure.unwrap_or(false);
+#[tauri::command]
+pub async fn list_displays(device: String, custom_path: Option<String>) -> Result<Vec<Display>, String> {
+    let output = scrcpy_list_output(&device, "--list-displays", custom_path).await?;
+    Ok(scrcpy_options::parse_displays(&output))
+}
 
-    if config.session_mode == "mirror" && otg_enabled && otg_pure {
-        if config.device.contains('.') || config.device.contains(':') {
-             args.push("--no-video".to_string());
-             args.push("--no-audio".to_string());
-             args.push("--keyboard=uhid".to_string());
-             args.push("--mouse=uhid".to_string());
-        } else {
-             args.push("--otg".to_string());
-        }
-    } else {
-        if let Some(bitrate) = config.bitrate {
-            args.push("--video-bit-rate".to_string());
-            args.push(format!("{}M", bitrate));
-        }
-        
-        if let Some(audio) = config.audio_enabled { if !audio { args.push("--no-audio".to_string()); } }
-        if let Some(aot) = config.always_on_top { if aot { args.push("--always-on-top".to_string()); } }
-        if let Some(fs) = config.fullscreen { if fs { args.push("--fullscreen".to_string()); } }
-        if let Some(bl) = config.borderless { if bl { args.push("--window-borderless".to_string()); } }
-        
-        if let Some(rot) = &config.rotation {
-            if rot != "0" {
-                args.push("--orientation".to_string());
-                args.push(rot.clone());
-            }
-        }
+#[tauri::command]
+pub async fn list_cameras(device: String, custom_path: Option<String>) -> Result<Vec<Camera>, String> {
+    let output = scrcpy_list_output(&device, "--list-cameras", custom_path).await?;
+    Ok(scrcpy_options::parse_cameras(&output))
+}
 
-        let can_control = config.session_mode != "camera";
-        if can_control {
-             if let Some(sa) = config.stay_awake { if sa { args.push("--stay-awake".to_string()); } }
-             if let Some(to) = config.turn_off { if to { args.push("--turn-screen-off".to_string()); args.push("--no-power-on".to_string()); } }
-        }
+#[tauri::command]
+pub async fn list_camera_sizes(device: String, custom_path: Option<String>) -> Result<Vec<Camera>, String> {
+    let output = scrcpy_list_output(&device, "--list-camera-sizes", custom_path).await?;
+    Ok(scrcpy_options::parse_cameras(&output))
+}
 
-        if config.session_mode == "camera" {
-            args.push("--video-source=camera".to_string());
-            if let Some(cid) = &config.camera_id {
-                if !cid.is_empty() { args.push(format!("--camera-id={}", cid)); }
-                else if let Some(facing) = &config.camera_facing { args.push(format!("--camera-facing={}", facing)); }
-            } else if let Some(facing) = &config.camera_facing { args.push(format!("--camera-facing={}", facing)); }
-            
-             // Resolution logic simplified for brevity (can expand)
-            if let Some(ar) = &config.camera_ar { if ar != "0" { args.push(format!("--camera-ar={}", ar)); } }
-            if let Some(chs) = config.camera_high_speed { if chs { args.push("--camera-high-speed".to_string()); } }
-             // fps handled generically below
-        } else if config.session_mode == "desktop" {
-             let w = config.vd_width.unwrap_or(1920);
-             let h = config.vd_height.unwrap_or(1080);
-             let dpi = config.vd_dpi.unwrap_or(420);
-             args.push(format!("--new-display={}x{}/{}", w, h, dpi));
-             args.push("--video-buffer=100".to_string());
-        } else {
-             if otg_enabled {
-                 args.push("--keyboard=uhid".to_string());
-                 args.push("--mouse=uhid".to_string());
-             }
-        }
-        
-        if let Some(fps) = config.fps {
-            if config.session_mode == "camera" {
-                args.push("--camera-fps".to_string());
-            } else {
-                args.push("--max-fps".to_string());
-            }
-            args.push(fps.to_string());
-        } else if config.session_mode == "camera" && config.camera_high_speed.unwrap_or(false) {
-            args.push("--camera-fps".to_string());
-            args.push("60".to_string());
-        }
+#[tauri::command]
+pub async fn list_apps(device: String, custom_path: Option<String>) -> Result<Vec<App>, String> {
+    let output = scrcpy_list_output(&device, "--list-apps", custom_path).await?;
+    Ok(scrcpy_options::parse_apps(&output))
+}
 
-        // Shared resolution logic (applies to mirror and camera in scrcpy 3.x)
-        if let Some(res) = &config.res { 
-            if res != "0" { 
-                args.push("--max-size".to_string()); 
-                args.push(res.clone()); 
-            } 
-        }
-        
-        if let Some(rec) = config.record {
-             if rec {
-                 let mut path = config.record_path.clone().unwrap_or_default();
-                 
-                 // If path is empty, try to get Video dir fallback
-                 if path.trim().is_empty() {
-                    path = video_dir_fallback.unwrap_or_else(|| ".".to_string());
-                 }
-
-                 let filename = format!("scrcpy_{}_{}.mkv", config.device.replace(":", "-"), chrono::Local::now().format("%Y%m%d_%H%M%S"));
-                 let full_path = std::path::Path::new(&path).join(filename);
-                 args.push(format!("--record={}", full_path.to_string_lossy()));
-             }
-        }
-    }
-    
-    args
+fn profile_store(app_handle: &tauri::AppHandle) -> Result<ProfileStore, String> {
+    let config_dir = app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(ProfileStore::new(&config_dir))
 }
 
 #[tauri::command]
-pub async fn run_scrcpy(window: Window, state: State<'_, ScrcpyState>, config: ScrcpyConfig, app_handle: tauri::AppHandle) -> Result<(), String> {
-    
+pub async fn list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<String>, String> {
+    profile_store(&app_handle)?.list()
+}
+
+#[tauri::command]
+pub async fn save_profile(app_handle: tauri::AppHandle, name: String, config: ScrcpyConfig) -> Result<(), String> {
+    profile_store(&app_handle)?.save(&name, &config)
+}
+
+#[tauri::command]
+pub async fn load_profile(app_handle: tauri::AppHandle, name: String) -> Result<ScrcpyConfig, String> {
+    profile_store(&app_handle)?.load(&name)
+}
+
+#[tauri::command]
+pub async fn rename_profile(app_handle: tauri::AppHandle, from: String, to: String) -> Result<(), String> {
+    profile_store(&app_handle)?.rename(&from, &to)
+}
+
+#[tauri::command]
+pub async fn delete_profile(app_handle: tauri::AppHandle, name: String) -> Result<(), String> {
+    profile_store(&app_handle)?.delete(&name)
+}
+
+#[tauri::command]
+pub async fn import_profile(app_handle: tauri::AppHandle, path: String, name: Option<String>) -> Result<String, String> {
+    profile_store(&app_handle)?.import(Path::new(&path), name.as_deref())
+}
+
+#[tauri::command]
+pub async fn export_profile(app_handle: tauri::AppHandle, name: String, path: String) -> Result<(), String> {
+    profile_store(&app_handle)?.export(&name, Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn get_device_profiles(app_handle: tauri::AppHandle) -> Result<std::collections::BTreeMap<String, String>, String> {
+    profile_store(&app_handle)?.device_defaults()
+}
+
+#[tauri::command]
+pub async fn set_device_profile(app_handle: tauri::AppHandle, serial: String, profile: Option<String>) -> Result<(), String> {
+    profile_store(&app_handle)?.set_device_default(&serial, profile.as_deref())
+}
+
+/// Forwards a scrcpy output line verbatim and as a classified `scrcpy-event`.
+fn emit_scrcpy_line(window: &Window, session_id: &str, line: String) {
+    let _ = window.emit("scrcpy-event", scrcpy_log::to_event(session_id, &line));
+    let _ = window.emit("scrcpy-log", line);
+}
+
+#[tauri::command]
+pub async fn run_scrcpy(
+    window: Window,
+    state: State<'_, ScrcpyState>,
+    config: Option<ScrcpyConfig>,
+    device: Option<String>,
+    profile: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    // Either a full config from the UI, or a device plus a named (or its default) profile
+    let config = match (config, device) {
+        (Some(config), _) => config,
+        (None, Some(device)) => profile_store(&app_handle)?.resolve(&device, profile.as_deref())?,
+        (None, None) => return Err("run_scrcpy needs either a config or a device".to_string()),
+    };
+
     let video_dir = app_handle.path().video_dir().ok().map(|p| p.to_string_lossy().to_string());
-    let args = build_scrcpy_args(&config, video_dir);
+    let args = ScrcpyArgsBuilder::new(&config)
+        .video_dir_fallback(video_dir.clone())
+        .build()?;
 
-    let exe_path = get_binary_path("scrcpy", config.scrcpy_path);
+    let exe_path = get_binary_path("scrcpy", config.scrcpy_path.clone());
     
     // Log the session details for the user
-    let mode_label = match config.session_mode.as_str() {
-        "camera" => "Camera Mode",
-        "desktop" => "Desktop Mode",
-        _ => "Screen Mirroring",
-    };
+    let mode_label = config.session_mode.label();
     
-    let res_label = config.res.as_ref().map(|r| if r == "0" { "Original" } else { r }).unwrap_or("Original");
+    let res_label = match config.res {
+        Some(MaxSize::Limit(size)) => size.to_string(),
+        _ => "Original".to_string(),
+    };
     let bitrate_label = format!("{}Mbps", config.bitrate.unwrap_or(8));
     let fps_label = format!("{}fps", config.fps.unwrap_or(60));
     
@@ -617,11 +1791,35 @@ pub async fn run_scrcpy(window: Window, state: State<'_, ScrcpyState>, config: S
         let _ = window.emit("scrcpy-log", format!("[SYSTEM] Recording enabled -> output to {}", path));
     }
 
-    let command_str = format!("> scrcpy {}", args.join(" "));
-    let _ = window.emit("scrcpy-log", command_str);
+    let session_id = format!("session-{}", state.next_session_id.fetch_add(1, Ordering::Relaxed));
+    let (child, stderr_task, tail) = spawn_scrcpy(&window, &session_id, &exe_path, &args)?;
+    let info = SessionInfo {
+        id: session_id.clone(),
+        device: config.device.clone(),
+        mode: config.session_mode,
+        started_at: chrono::Local::now().to_rfc3339(),
+        pid: child.id(),
+        args,
+    };
 
-    let mut command = create_command(&exe_path);
-    command.args(&args);
+    // Store session, the child itself is handed to the supervisor below
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+    state.processes.lock().unwrap().insert(session_id.clone(), ScrcpySession { info, control: control_tx });
+    let _ = window.emit("scrcpy-status", json!({ "sessionId": session_id, "device": config.device, "running": true }));
+
+    tokio::spawn(monitor_session(window.clone(), session_id.clone(), config, video_dir, exe_path, control_rx, (child, stderr_task, tail)));
+
+    Ok(session_id)
+}
+
+type SpawnedScrcpy = (tokio::process::Child, tokio::task::JoinHandle<()>, StderrTail);
+
+/// Starts scrcpy with its output forwarded to the window under `session_id`.
+fn spawn_scrcpy(window: &Window, session_id: &str, exe_path: &str, args: &[String]) -> Result<SpawnedScrcpy, String> {
+    let _ = window.emit("scrcpy-log", format!("> scrcpy {}", args.join(" ")));
+
+    let mut command = create_command(exe_path);
+    command.args(args);
     command.stdout(Stdio::piped());
     command.stderr(Stdio::piped());
 
@@ -631,216 +1829,240 @@ pub async fn run_scrcpy(window: Window, state: State<'_, ScrcpyState>, config: S
     let stderr = child.stderr.take().expect("Failed to capture stderr");
     
     let window_clone = window.clone();
+    let session_out = session_id.to_string();
     tokio::spawn(async move {
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
-            let _ = window_clone.emit("scrcpy-log", line);
+            emit_scrcpy_line(&window_clone, &session_out, line);
         }
     });
 
     let window_clone2 = window.clone();
-    tokio::spawn(async move {
+    let session_err = session_id.to_string();
+    let tail = StderrTail::default();
+    let stderr_tail = tail.clone();
+    let stderr_task = tokio::spawn(async move {
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
-            let _ = window_clone2.emit("scrcpy-log", line); // Scrcpy sends logs to stderr mostly
+            stderr_tail.push(&line);
+            emit_scrcpy_line(&window_clone2, &session_err, line); // Scrcpy sends logs to stderr mostly
         }
     });
 
-    // Store process
-    state.processes.lock().unwrap().insert(config.device.clone(), child);
-    let _ = window.emit("scrcpy-status", json!({ "device": config.device, "running": true }));
+    Ok((child, stderr_task, tail))
+}
+
+/// Re-runs `adb connect` for a wireless serial before a restart.
+async fn reconnect_tcp(adb_path: &str, serial: &str) -> String {
+    match timeout(Duration::from_secs(5), AdbClient::default().connect_device(serial)).await {
+        Ok(Ok(message)) => return message,
+        Ok(Err(AdbError::Failed(e))) => return e,
+        Ok(Err(AdbError::Unavailable(_))) => {}
+        Err(_) => return "connection timed out".to_string(),
+    }
+
+    let output = create_command(adb_path).arg("connect").arg(serial).output();
+    match timeout(Duration::from_secs(5), output).await {
+        Ok(Ok(output)) => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "connection timed out".to_string(),
+    }
+}
 
-    // Monitor for exit
-    let device_mon = config.device.clone();
-    let window_mon = window.clone();
+/// Supervises a session until it ends for good, relaunching it according to
+/// the config's restart policy, then removes it from the state and reports the exit.
+async fn monitor_session(
+    window: Window,
+    session_id: String,
+    config: ScrcpyConfig,
+    video_dir: Option<String>,
+    exe_path: String,
+    mut control_rx: mpsc::UnboundedReceiver<SessionControl>,
+    spawned: SpawnedScrcpy,
+) {
+    // Auto-restart only makes sense for wireless devices, a USB one that
+    // disappears has usually been unplugged on purpose
+    let policy = config.restart_policy.clone().filter(|_| config.device.contains(':'));
+    let adb_path = get_binary_path("adb", config.scrcpy_path.clone());
     let app_handle = window.app_handle().clone();
 
-    tokio::spawn(async move {
+    let (mut child, mut stderr_task, mut tail) = spawned;
+    let mut attempt = 0;
+
+    let exit = 'session: loop {
+        let started = std::time::Instant::now();
+        let mut exit = supervise(child, &mut control_rx, stderr_task, tail).await;
+        log_exit(&window, &exit);
+
+        let Some(policy) = &policy else { break exit };
+        // A session that ran for a while before dropping gets a fresh set of retries
+        if started.elapsed() > Duration::from_secs(60) {
+            attempt = 0;
+        }
+
         loop {
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            
-            let state_mon = app_handle.state::<ScrcpyState>();
-            let mut processes = state_mon.processes.lock().unwrap();
-            if let Some(child) = processes.get_mut(&device_mon) {
-                // Explicitly use tokio's try_wait to help inference
-                match child.try_wait() {
-                    Ok(Some(status)) => {
-                        let _ = window_mon.emit("scrcpy-log", format!("[SYSTEM] Scrcpy process exited with status: {}", status));
-                        let _ = window_mon.emit("scrcpy-status", json!({ "device": device_mon, "running": false }));
-                        processes.remove(&device_mon);
-                        break;
-                    }
-                    Ok(None) => {
-                        // Still running
-                    }
-                    Err(e) => {
-                        let _ = window_mon.emit("scrcpy-log", format!("[SYSTEM] Error waiting for scrcpy: {}", e));
-                        let _ = window_mon.emit("scrcpy-status", json!({ "device": device_mon, "running": false }));
-                        processes.remove(&device_mon);
-                        break;
+            attempt += 1;
+            if !policy.should_restart(attempt, exit.success, exit.requested) {
+                if !exit.requested && attempt > policy.max_retries {
+                    emit_restart(&window, &session_id, &config.device, attempt - 1, policy.max_retries, "gave-up", None);
+                }
+                break 'session exit;
+            }
+
+            let delay = policy.backoff(attempt);
+            let _ = window.emit("scrcpy-log", format!("[SYSTEM] Restarting session in {}ms (attempt {}/{})", delay.as_millis(), attempt, policy.max_retries));
+            emit_restart(&window, &session_id, &config.device, attempt, policy.max_retries, "waiting", Some(delay.as_millis() as u64));
+
+            // Stay stoppable while waiting for the next attempt
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                Some(SessionControl::Stop { reply, .. }) = control_rx.recv() => {
+                    exit.requested = true;
+                    let _ = reply.send(exit.clone());
+                    break 'session exit;
+                }
+            }
+
+            emit_restart(&window, &session_id, &config.device, attempt, policy.max_retries, "reconnecting", None);
+            let message = reconnect_tcp(&adb_path, &config.device).await;
+            let _ = window.emit("scrcpy-log", format!("[ADB] {}", message));
+
+            // Rebuilt rather than reused so a recording goes to a new file instead of overwriting the last one
+            let relaunched = ScrcpyArgsBuilder::new(&config)
+                .video_dir_fallback(video_dir.clone())
+                .build()
+                .and_then(|args| spawn_scrcpy(&window, &session_id, &exe_path, &args).map(|spawned| (args, spawned)));
+
+            match relaunched {
+                Ok((args, spawned)) => {
+                    if let Some(session) = app_handle.state::<ScrcpyState>().processes.lock().unwrap().get_mut(&session_id) {
+                        session.info.pid = spawned.0.id();
+                        session.info.args = args;
                     }
+                    emit_restart(&window, &session_id, &config.device, attempt, policy.max_retries, "restarted", None);
+                    (child, stderr_task, tail) = spawned;
+                    continue 'session;
+                }
+                Err(e) => {
+                    let _ = window.emit("scrcpy-log", format!("[SYSTEM] Restart failed: {}", e));
+                    exit.error = Some(e);
                 }
-            } else {
-                // Process removed manually (stop_scrcpy) or was never added (rare error)
-                // We emit just in case to sync UI
-                let _ = window_mon.emit("scrcpy-status", json!({ "device": device_mon, "running": false }));
-                break;
             }
         }
-    });
+    };
 
-    Ok(())
+    app_handle.state::<ScrcpyState>().processes.lock().unwrap().remove(&session_id);
+    let _ = window.emit("scrcpy-status", json!({ "sessionId": session_id, "device": config.device, "running": false, "exit": exit }));
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_build_scrcpy_args_mirror_defaults() {
-        let config = ScrcpyConfig {
-            device: "device1".to_string(),
-            session_mode: "mirror".to_string(),
-            bitrate: None,
-            fps: None,
-            stay_awake: None,
-            turn_off: None,
-            audio_enabled: None,
-            always_on_top: None,
-            fullscreen: None,
-            borderless: None,
-            record: None,
-            record_path: None,
-            scrcpy_path: None,
-            otg_enabled: None,
-            otg_pure: None,
-            camera_facing: None,
-            camera_id: None,
-            codec: None,
-            camera_ar: None,
-            camera_high_speed: None,
-            vd_width: None,
-            vd_height: None,
-            vd_dpi: None,
-            rotation: None,
-            res: None,
-        };
+fn log_exit(window: &Window, exit: &ExitInfo) {
+    let reason = match (&exit.error, exit.code, exit.signal) {
+        (Some(e), _, _) => format!("error: {}", e),
+        (None, Some(code), _) => format!("exit code {}", code),
+        (None, None, Some(signal)) => format!("signal {}", signal),
+        _ => "unknown status".to_string(),
+    };
+    let _ = window.emit("scrcpy-log", format!("[SYSTEM] Scrcpy process exited with {}", reason));
+}
 
-        let args = build_scrcpy_args(&config, None);
-        assert!(args.contains(&"-s".to_string()));
-        assert!(args.contains(&"device1".to_string()));
-        assert!(args.contains(&"--video-codec=h264".to_string()));
-    }
-
-    #[test]
-    fn test_build_scrcpy_args_camera_mode() {
-        let config = ScrcpyConfig {
-            device: "device1".to_string(),
-            session_mode: "camera".to_string(),
-            bitrate: None,
-            fps: Some(30),
-            stay_awake: None,
-            turn_off: None,
-            audio_enabled: None,
-            always_on_top: None,
-            fullscreen: None,
-            borderless: None,
-            record: None,
-            record_path: None,
-            scrcpy_path: None,
-            otg_enabled: None,
-            otg_pure: None,
-            camera_facing: Some("front".to_string()),
-            camera_id: None,
-            codec: None,
-            camera_ar: None,
-            camera_high_speed: None,
-            vd_width: None,
-            vd_height: None,
-            vd_dpi: None,
-            rotation: None,
-            res: None,
-        };
+/// Emits `scrcpy-restart` progress: `waiting` (with the delay), `reconnecting`, `restarted` or `gave-up`.
+fn emit_restart(window: &Window, session_id: &str, device: &str, attempt: u32, max_retries: u32, status: &str, delay_ms: Option<u64>) {
+    let _ = window.emit("scrcpy-restart", json!({
+        "sessionId": session_id,
+        "device": device,
+        "attempt": attempt,
+        "maxRetries": max_retries,
+        "status": status,
+        "delayMs": delay_ms,
+    }));
+}
 
-        let args = build_scrcpy_args(&config, None);
-        assert!(args.contains(&"--video-source=camera".to_string()));
-        assert!(args.contains(&"--camera-facing=front".to_string()));
-        assert!(args.contains(&"--camera-fps".to_string()));
-        assert!(args.contains(&"30".to_string()));
-    }
-
-    #[test]
-    fn test_build_scrcpy_args_bitrate_and_fps() {
-        let config = ScrcpyConfig {
-            device: "device1".to_string(),
-            session_mode: "mirror".to_string(),
-            bitrate: Some(8),
-            fps: Some(60),
-            stay_awake: None,
-            turn_off: None,
-            audio_enabled: None,
-            always_on_top: None,
-            fullscreen: None,
-            borderless: None,
-            record: None,
-            record_path: None,
-            scrcpy_path: None,
-            otg_enabled: None,
-            otg_pure: None,
-            camera_facing: None,
-            camera_id: None,
-            codec: None,
-            camera_ar: None,
-            camera_high_speed: None,
-            vd_width: None,
-            vd_height: None,
-            vd_dpi: None,
-            rotation: None,
-            res: None,
-        };
+#[tauri::command]
+pub async fn list_sessions(state: State<'_, ScrcpyState>) -> Result<Vec<SessionInfo>, String> {
+    let processes = state.processes.lock().unwrap();
+    let mut sessions: Vec<SessionInfo> = processes.values().map(|s| s.info.clone()).collect();
+    sessions.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(sessions)
+}
 
-        let args = build_scrcpy_args(&config, None);
-        assert!(args.contains(&"--video-bit-rate".to_string()));
-        assert!(args.contains(&"8M".to_string()));
-        assert!(args.contains(&"--max-fps".to_string()));
-        assert!(args.contains(&"60".to_string()));
-    }
+/// Stops a session, waiting up to `grace_ms` for scrcpy to exit on its own
+/// (so recordings get finalized) before killing it.
+#[tauri::command]
+pub async fn stop_scrcpy(state: State<'_, ScrcpyState>, session_id: String, grace_ms: Option<u64>) -> Result<StopReport, String> {
+    let grace = grace_ms.map(Duration::from_millis).unwrap_or(DEFAULT_STOP_GRACE);
+    let pending = {
+        let processes = state.processes.lock().unwrap();
+        let session = processes.get(&session_id).ok_or_else(|| format!("No running session {}", session_id))?;
+        request_stop(&session_id, session, grace)?
+    };
+    wait_stopped(pending).await
 }
 
+/// Stops every running session, all of them sharing the same grace period.
 #[tauri::command]
-pub async fn stop_scrcpy(state: State<'_, ScrcpyState>, device: String) -> Result<(), String> {
-    let child = {
+pub async fn stop_all_sessions(state: State<'_, ScrcpyState>, grace_ms: Option<u64>) -> Result<StopAllReport, String> {
+    let grace = grace_ms.map(Duration::from_millis).unwrap_or(DEFAULT_STOP_GRACE);
+    Ok(stop_all(&state, grace).await)
+}
+
+/// Also used on app exit. Sessions are taken out of the state right away so
+/// nothing else tries to stop or restart them in the meantime.
+pub async fn stop_all(state: &ScrcpyState, grace: Duration) -> StopAllReport {
+    let mut report = StopAllReport::default();
+    let pending: Vec<PendingStop> = {
         let mut processes = state.processes.lock().unwrap();
-        processes.remove(&device)
+        processes
+            .drain()
+            .filter_map(|(id, session)| match request_stop(&id, &session, grace) {
+                Ok(pending) => Some(pending),
+                Err(error) => {
+                    report.failed.push(StopFailure { session_id: id, error });
+                    None
+                }
+            })
+            .collect()
     };
 
-    if let Some(mut c) = child {
-        if let Some(pid) = c.id() {
-             #[cfg(target_os = "windows")]
-             {
-                let _ = StdCommand::new("taskkill")
-                    .args(&["/PID", &pid.to_string()])
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .output();
-             }
-             
-             #[cfg(not(target_os = "windows"))]
-             {
-                 // Try graceful termination first via SIGTERM
-                 let _ = StdCommand::new("kill")
-                    .arg(pid.to_string())
-                    .output();
-             }
-
-            // Give it a moment to finalize, but don't block too long
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-        } else {
-            let _ = c.kill().await;
+    for stop in pending {
+        let session_id = stop.session_id.clone();
+        match wait_stopped(stop).await {
+            Ok(stopped) => report.stopped.push(stopped),
+            Err(error) => report.failed.push(StopFailure { session_id, error }),
         }
     }
-    Ok(())
+    report
+}
+
+struct PendingStop {
+    session_id: String,
+    args: Vec<String>,
+    grace: Duration,
+    reply: oneshot::Receiver<ExitInfo>,
+}
+
+fn request_stop(session_id: &str, session: &ScrcpySession, grace: Duration) -> Result<PendingStop, String> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    session
+        .control
+        .send(SessionControl::Stop { grace, reply: reply_tx })
+        .map_err(|_| format!("Session {} already ended", session_id))?;
+
+    Ok(PendingStop { session_id: session_id.to_string(), args: session.info.args.clone(), grace, reply: reply_rx })
+}
+
+async fn wait_stopped(pending: PendingStop) -> Result<StopReport, String> {
+    let PendingStop { session_id, args, grace, reply } = pending;
+
+    // The supervisor kills the process once the grace period is over, so this only guards against a hung wait
+    let exit = timeout(grace + Duration::from_secs(5), reply)
+        .await
+        .map_err(|_| format!("Session {} didn't exit after being killed", session_id))?
+        .map_err(|_| format!("Session {} already ended", session_id))?;
+
+    let recording = recording::recording_path(&args).map(|path| recording::check_recording(Path::new(path)));
+    Ok(StopReport { session_id, exit, recording })
 }
 
 #[tauri::command]
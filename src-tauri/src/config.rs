@@ -0,0 +1,687 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMode {
+    #[default]
+    Mirror,
+    Camera,
+    Desktop,
+}
+
+impl SessionMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SessionMode::Mirror => "Screen Mirroring",
+            SessionMode::Camera => "Camera Mode",
+            SessionMode::Desktop => "Desktop Mode",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Av1,
+}
+
+impl VideoCodec {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+            VideoCodec::Av1 => "av1",
+        }
+    }
+}
+
+/// Values accepted by scrcpy's `--orientation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Orientation {
+    #[default]
+    #[serde(rename = "0")]
+    Natural,
+    #[serde(rename = "90")]
+    Rotate90,
+    #[serde(rename = "180")]
+    Rotate180,
+    #[serde(rename = "270")]
+    Rotate270,
+    #[serde(rename = "flip0")]
+    Flip0,
+    #[serde(rename = "flip90")]
+    Flip90,
+    #[serde(rename = "flip180")]
+    Flip180,
+    #[serde(rename = "flip270")]
+    Flip270,
+}
+
+impl Orientation {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            Orientation::Natural => "0",
+            Orientation::Rotate90 => "90",
+            Orientation::Rotate180 => "180",
+            Orientation::Rotate270 => "270",
+            Orientation::Flip0 => "flip0",
+            Orientation::Flip90 => "flip90",
+            Orientation::Flip180 => "flip180",
+            Orientation::Flip270 => "flip270",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraFacing {
+    Front,
+    Back,
+    External,
+}
+
+impl CameraFacing {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            CameraFacing::Front => "front",
+            CameraFacing::Back => "back",
+            CameraFacing::External => "external",
+        }
+    }
+}
+
+/// Values accepted by scrcpy's `--audio-source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioSource {
+    Output,
+    Playback,
+    Mic,
+    MicUnprocessed,
+    MicCamcorder,
+    MicVoiceRecognition,
+    MicVoiceCommunication,
+    VoiceCall,
+    VoiceCallUplink,
+    VoiceCallDownlink,
+    VoicePerformance,
+}
+
+impl AudioSource {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioSource::Output => "output",
+            AudioSource::Playback => "playback",
+            AudioSource::Mic => "mic",
+            AudioSource::MicUnprocessed => "mic-unprocessed",
+            AudioSource::MicCamcorder => "mic-camcorder",
+            AudioSource::MicVoiceRecognition => "mic-voice-recognition",
+            AudioSource::MicVoiceCommunication => "mic-voice-communication",
+            AudioSource::VoiceCall => "voice-call",
+            AudioSource::VoiceCallUplink => "voice-call-uplink",
+            AudioSource::VoiceCallDownlink => "voice-call-downlink",
+            AudioSource::VoicePerformance => "voice-performance",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Opus,
+    Aac,
+    Flac,
+    Raw,
+}
+
+impl AudioCodec {
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "opus",
+            AudioCodec::Aac => "aac",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Raw => "raw",
+        }
+    }
+}
+
+/// `--max-size` value. The frontend sends `"0"` for the original resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "MaxSizeRepr", into = "String")]
+pub enum MaxSize {
+    #[default]
+    Original,
+    Limit(u32),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaxSizeRepr {
+    Number(u32),
+    Text(String),
+}
+
+impl TryFrom<MaxSizeRepr> for MaxSize {
+    type Error = String;
+
+    fn try_from(repr: MaxSizeRepr) -> Result<Self, Self::Error> {
+        let value = match repr {
+            MaxSizeRepr::Number(n) => n,
+            MaxSizeRepr::Text(s) => s.trim().parse().map_err(|_| format!("Invalid resolution: {}", s))?,
+        };
+        Ok(if value == 0 { MaxSize::Original } else { MaxSize::Limit(value) })
+    }
+}
+
+impl From<MaxSize> for String {
+    fn from(size: MaxSize) -> Self {
+        match size {
+            MaxSize::Original => "0".to_string(),
+            MaxSize::Limit(n) => n.to_string(),
+        }
+    }
+}
+
+/// Opt-in relaunch behaviour for wireless sessions whose scrcpy process exits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RestartPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each following one.
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Don't restart after a clean exit (e.g. the user closed the scrcpy window).
+    pub only_on_abnormal_exit: bool,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3, backoff_ms: 1000, max_backoff_ms: 30_000, only_on_abnormal_exit: true }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before retry number `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+
+    /// Whether an exit qualifies for retry number `attempt`. Exits requested
+    /// through `stop_scrcpy` never do.
+    pub fn should_restart(&self, attempt: u32, success: bool, requested: bool) -> bool {
+        !requested && attempt <= self.max_retries && !(success && self.only_on_abnormal_exit)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrcpyConfig {
+    #[serde(default)]
+    pub device: String,
+    pub session_mode: SessionMode,
+    // ... other fields matching JS ...
+    pub bitrate: Option<u32>,
+    pub fps: Option<u32>,
+    pub stay_awake: Option<bool>,
+    pub turn_off: Option<bool>,
+    pub audio_enabled: Option<bool>,
+    pub always_on_top: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub borderless: Option<bool>,
+    pub record: Option<bool>,
+    pub record_path: Option<String>,
+    pub scrcpy_path: Option<String>,
+    pub otg_enabled: Option<bool>,
+    pub otg_pure: Option<bool>,
+    pub camera_facing: Option<CameraFacing>,
+    pub camera_id: Option<String>,
+    pub codec: Option<VideoCodec>,
+    pub camera_ar: Option<String>,
+    pub camera_high_speed: Option<bool>,
+    pub vd_width: Option<u32>,
+    pub vd_height: Option<u32>,
+    pub vd_dpi: Option<u32>,
+    pub rotation: Option<Orientation>,
+    pub res: Option<MaxSize>,
+    pub audio_source: Option<AudioSource>,
+    pub audio_codec: Option<AudioCodec>,
+    /// In Kbps.
+    pub audio_bitrate: Option<u32>,
+    /// In milliseconds.
+    pub audio_buffer: Option<u32>,
+    pub audio_dup: Option<bool>,
+    pub audio_playback: Option<bool>,
+    /// Only applied to wireless (`ip:port`) devices.
+    pub restart_policy: Option<RestartPolicy>,
+}
+
+/// Turns a `ScrcpyConfig` into scrcpy command line arguments, rejecting
+/// combinations scrcpy would refuse or silently ignore.
+pub struct ScrcpyArgsBuilder<'a> {
+    config: &'a ScrcpyConfig,
+    video_dir_fallback: Option<String>,
+}
+
+impl<'a> ScrcpyArgsBuilder<'a> {
+    pub fn new(config: &'a ScrcpyConfig) -> Self {
+        Self { config, video_dir_fallback: None }
+    }
+
+    /// Directory used for recordings when `record_path` is empty.
+    pub fn video_dir_fallback(mut self, dir: Option<String>) -> Self {
+        self.video_dir_fallback = dir;
+        self
+    }
+
+    fn is_pure_otg(&self) -> bool {
+        self.config.session_mode == SessionMode::Mirror
+            && self.config.otg_enabled.unwrap_or(false)
+            && self.config.otg_pure.unwrap_or(false)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let config = self.config;
+
+        if config.bitrate == Some(0) {
+            return Err("Bitrate must be greater than 0".to_string());
+        }
+        if config.fps == Some(0) {
+            return Err("FPS must be greater than 0".to_string());
+        }
+        if self.is_pure_otg() && config.record.unwrap_or(false) {
+            return Err("Recording is not available in pure OTG mode, there is no video stream".to_string());
+        }
+        if config.session_mode == SessionMode::Desktop
+            && [config.vd_width, config.vd_height, config.vd_dpi].contains(&Some(0))
+        {
+            return Err("Virtual display width, height and DPI must be greater than 0".to_string());
+        }
+        if config.audio_enabled != Some(false) {
+            if config.audio_bitrate == Some(0) {
+                return Err("Audio bitrate must be greater than 0".to_string());
+            }
+            if config.audio_dup.unwrap_or(false) && config.audio_source.is_some_and(|s| s != AudioSource::Playback) {
+                return Err("Audio duplication requires the playback audio source".to_string());
+            }
+            if config.audio_codec == Some(AudioCodec::Raw) && config.record.unwrap_or(false) {
+                return Err("Raw audio can't be recorded, pick opus, aac or flac".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Vec<String>, String> {
+        self.validate()?;
+
+        let config = self.config;
+        let mut args = Vec::new();
+
+        // Construct arguments based on config
+        if !config.device.is_empty() {
+            args.push("-s".to_string());
+            args.push(config.device.clone());
+        }
+
+        let codec = config.codec.unwrap_or_default();
+        args.push(format!("--video-codec={}", codec.as_arg()));
+
+        let otg_enabled = config.otg_enabled.unwrap_or(false);
+        let is_camera = config.session_mode == SessionMode::Camera;
+
+        if self.is_pure_otg() {
+            if config.device.contains('.') || config.device.contains(':') {
+                args.push("--no-video".to_string());
+                args.push("--no-audio".to_string());
+                args.push("--keyboard=uhid".to_string());
+                args.push("--mouse=uhid".to_string());
+            } else {
+                args.push("--otg".to_string());
+            }
+            return Ok(args);
+        }
+
+        if let Some(bitrate) = config.bitrate {
+            args.push("--video-bit-rate".to_string());
+            args.push(format!("{}M", bitrate));
+        }
+
+        if config.audio_enabled == Some(false) {
+            args.push("--no-audio".to_string());
+        } else {
+            if let Some(source) = config.audio_source { args.push(format!("--audio-source={}", source.as_arg())); }
+            if let Some(codec) = config.audio_codec { args.push(format!("--audio-codec={}", codec.as_arg())); }
+            if let Some(bitrate) = config.audio_bitrate { args.push(format!("--audio-bit-rate={}K", bitrate)); }
+            if let Some(buffer) = config.audio_buffer { args.push(format!("--audio-buffer={}", buffer)); }
+            if config.audio_dup.unwrap_or(false) { args.push("--audio-dup".to_string()); }
+            if config.audio_playback == Some(false) { args.push("--no-audio-playback".to_string()); }
+        }
+        if config.always_on_top.unwrap_or(false) { args.push("--always-on-top".to_string()); }
+        if config.fullscreen.unwrap_or(false) { args.push("--fullscreen".to_string()); }
+        if config.borderless.unwrap_or(false) { args.push("--window-borderless".to_string()); }
+
+        if let Some(rot) = config.rotation {
+            if rot != Orientation::Natural {
+                args.push("--orientation".to_string());
+                args.push(rot.as_arg().to_string());
+            }
+        }
+
+        // The camera can't be controlled, so power/awake flags don't apply
+        if !is_camera {
+            if config.stay_awake.unwrap_or(false) { args.push("--stay-awake".to_string()); }
+            if config.turn_off.unwrap_or(false) {
+                args.push("--turn-screen-off".to_string());
+                args.push("--no-power-on".to_string());
+            }
+        }
+
+        match config.session_mode {
+            SessionMode::Camera => {
+                args.push("--video-source=camera".to_string());
+                // An explicit camera id wins over the facing preference
+                match (config.camera_id.as_deref().filter(|id| !id.is_empty()), config.camera_facing) {
+                    (Some(cid), _) => args.push(format!("--camera-id={}", cid)),
+                    (None, Some(facing)) => args.push(format!("--camera-facing={}", facing.as_arg())),
+                    (None, None) => {}
+                }
+
+                if let Some(ar) = &config.camera_ar { if ar != "0" { args.push(format!("--camera-ar={}", ar)); } }
+                if config.camera_high_speed.unwrap_or(false) { args.push("--camera-high-speed".to_string()); }
+            }
+            SessionMode::Desktop => {
+                let w = config.vd_width.unwrap_or(1920);
+                let h = config.vd_height.unwrap_or(1080);
+                let dpi = config.vd_dpi.unwrap_or(420);
+                args.push(format!("--new-display={}x{}/{}", w, h, dpi));
+                args.push("--video-buffer=100".to_string());
+            }
+            SessionMode::Mirror => {
+                if otg_enabled {
+                    args.push("--keyboard=uhid".to_string());
+                    args.push("--mouse=uhid".to_string());
+                }
+            }
+        }
+
+        if let Some(fps) = config.fps {
+            args.push(if is_camera { "--camera-fps" } else { "--max-fps" }.to_string());
+            args.push(fps.to_string());
+        } else if is_camera && config.camera_high_speed.unwrap_or(false) {
+            args.push("--camera-fps".to_string());
+            args.push("60".to_string());
+        }
+
+        // Shared resolution logic (applies to mirror and camera in scrcpy 3.x)
+        if let Some(MaxSize::Limit(size)) = config.res {
+            args.push("--max-size".to_string());
+            args.push(size.to_string());
+        }
+
+        if config.record.unwrap_or(false) {
+            let mut path = config.record_path.clone().unwrap_or_default();
+
+            // If path is empty, try to get Video dir fallback
+            if path.trim().is_empty() {
+                path = self.video_dir_fallback.unwrap_or_else(|| ".".to_string());
+            }
+
+            let filename = format!("scrcpy_{}_{}.mkv", config.device.replace(":", "-"), chrono::Local::now().format("%Y%m%d_%H%M%S"));
+            let full_path = std::path::Path::new(&path).join(filename);
+            args.push(format!("--record={}", full_path.to_string_lossy()));
+        }
+
+        Ok(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(config: &ScrcpyConfig) -> Vec<String> {
+        ScrcpyArgsBuilder::new(config).build().unwrap()
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_mirror_defaults() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            session_mode: SessionMode::Mirror,
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"-s".to_string()));
+        assert!(args.contains(&"device1".to_string()));
+        assert!(args.contains(&"--video-codec=h264".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_camera_mode() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            session_mode: SessionMode::Camera,
+            fps: Some(30),
+            camera_facing: Some(CameraFacing::Front),
+            stay_awake: Some(true),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"--video-source=camera".to_string()));
+        assert!(args.contains(&"--camera-facing=front".to_string()));
+        assert!(args.contains(&"--camera-fps".to_string()));
+        assert!(args.contains(&"30".to_string()));
+        assert!(!args.contains(&"--stay-awake".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_camera_id_and_high_speed() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            session_mode: SessionMode::Camera,
+            camera_id: Some("2".to_string()),
+            camera_facing: Some(CameraFacing::Back),
+            camera_ar: Some("16:9".to_string()),
+            camera_high_speed: Some(true),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"--camera-id=2".to_string()));
+        assert!(!args.contains(&"--camera-facing=back".to_string()));
+        assert!(args.contains(&"--camera-ar=16:9".to_string()));
+        assert!(args.contains(&"--camera-high-speed".to_string()));
+        assert!(args.contains(&"60".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_bitrate_and_fps() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            session_mode: SessionMode::Mirror,
+            bitrate: Some(8),
+            fps: Some(60),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"--video-bit-rate".to_string()));
+        assert!(args.contains(&"8M".to_string()));
+        assert!(args.contains(&"--max-fps".to_string()));
+        assert!(args.contains(&"60".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_mirror_options() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            codec: Some(VideoCodec::H265),
+            rotation: Some(Orientation::Rotate90),
+            res: Some(MaxSize::Limit(1920)),
+            audio_enabled: Some(false),
+            always_on_top: Some(true),
+            fullscreen: Some(true),
+            borderless: Some(true),
+            stay_awake: Some(true),
+            turn_off: Some(true),
+            otg_enabled: Some(true),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        for expected in [
+            "--video-codec=h265", "--orientation", "90", "--max-size", "1920", "--no-audio",
+            "--always-on-top", "--fullscreen", "--window-borderless", "--stay-awake",
+            "--turn-screen-off", "--no-power-on", "--keyboard=uhid", "--mouse=uhid",
+        ] {
+            assert!(args.contains(&expected.to_string()), "missing {}", expected);
+        }
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_desktop_mode() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            session_mode: SessionMode::Desktop,
+            vd_width: Some(2560),
+            vd_height: Some(1440),
+            vd_dpi: Some(320),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"--new-display=2560x1440/320".to_string()));
+        assert!(args.contains(&"--video-buffer=100".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_pure_otg() {
+        let mut config = ScrcpyConfig {
+            device: "R58M123ABC".to_string(),
+            otg_enabled: Some(true),
+            otg_pure: Some(true),
+            bitrate: Some(8),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        assert!(args.contains(&"--otg".to_string()));
+        assert!(!args.contains(&"--video-bit-rate".to_string()));
+
+        // Wireless devices can't use --otg, fall back to uhid without streams
+        config.device = "192.168.1.5:5555".to_string();
+        let args = build(&config);
+        assert!(args.contains(&"--no-video".to_string()));
+        assert!(args.contains(&"--keyboard=uhid".to_string()));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_record_path() {
+        let config = ScrcpyConfig {
+            device: "192.168.1.5:5555".to_string(),
+            record: Some(true),
+            ..Default::default()
+        };
+
+        let args = ScrcpyArgsBuilder::new(&config)
+            .video_dir_fallback(Some("videos".to_string()))
+            .build()
+            .unwrap();
+        let record = args.iter().find(|a| a.starts_with("--record=")).unwrap();
+        assert!(record.contains("videos"));
+        assert!(record.contains("scrcpy_192.168.1.5-5555_"));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_audio_options() {
+        let config = ScrcpyConfig {
+            device: "device1".to_string(),
+            audio_source: Some(AudioSource::Playback),
+            audio_codec: Some(AudioCodec::Aac),
+            audio_bitrate: Some(128),
+            audio_buffer: Some(50),
+            audio_dup: Some(true),
+            audio_playback: Some(false),
+            ..Default::default()
+        };
+
+        let args = build(&config);
+        for expected in [
+            "--audio-source=playback", "--audio-codec=aac", "--audio-bit-rate=128K",
+            "--audio-buffer=50", "--audio-dup", "--no-audio-playback",
+        ] {
+            assert!(args.contains(&expected.to_string()), "missing {}", expected);
+        }
+
+        // Audio settings are dropped entirely when audio is off
+        let config = ScrcpyConfig { audio_enabled: Some(false), ..config };
+        let args = build(&config);
+        assert!(args.contains(&"--no-audio".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("--audio-")));
+    }
+
+    #[test]
+    fn test_build_scrcpy_args_rejects_invalid_combinations() {
+        let invalid = [
+            ScrcpyConfig { fps: Some(0), ..Default::default() },
+            ScrcpyConfig { bitrate: Some(0), ..Default::default() },
+            ScrcpyConfig { otg_enabled: Some(true), otg_pure: Some(true), record: Some(true), ..Default::default() },
+            ScrcpyConfig { session_mode: SessionMode::Desktop, vd_dpi: Some(0), ..Default::default() },
+            ScrcpyConfig { audio_dup: Some(true), audio_source: Some(AudioSource::Mic), ..Default::default() },
+            ScrcpyConfig { audio_codec: Some(AudioCodec::Raw), record: Some(true), ..Default::default() },
+        ];
+        for config in &invalid {
+            assert!(ScrcpyArgsBuilder::new(config).build().is_err(), "{:?} should be rejected", config);
+        }
+    }
+
+    #[test]
+    fn test_restart_policy() {
+        let policy: RestartPolicy = serde_json::from_value(serde_json::json!({ "maxRetries": 2, "backoffMs": 500, "maxBackoffMs": 1500 })).unwrap();
+        assert!(policy.only_on_abnormal_exit);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1500));
+
+        assert!(policy.should_restart(1, false, false));
+        assert!(policy.should_restart(2, false, false));
+        assert!(!policy.should_restart(3, false, false));
+        assert!(!policy.should_restart(1, true, false));
+        assert!(!policy.should_restart(1, false, true));
+
+        let always = RestartPolicy { only_on_abnormal_exit: false, ..policy };
+        assert!(always.should_restart(1, true, false));
+    }
+
+    #[test]
+    fn test_config_deserializes_frontend_values() {
+        let config: ScrcpyConfig = serde_json::from_value(serde_json::json!({
+            "device": "device1",
+            "sessionMode": "camera",
+            "codec": "av1",
+            "rotation": "270",
+            "cameraFacing": "external",
+            "res": "0",
+            "audioSource": "mic-voice-communication",
+            "audioCodec": "flac",
+        }))
+        .unwrap();
+        assert_eq!(config.session_mode, SessionMode::Camera);
+        assert_eq!(config.codec, Some(VideoCodec::Av1));
+        assert_eq!(config.rotation, Some(Orientation::Rotate270));
+        assert_eq!(config.camera_facing, Some(CameraFacing::External));
+        assert_eq!(config.res, Some(MaxSize::Original));
+        assert_eq!(config.audio_source, Some(AudioSource::MicVoiceCommunication));
+        assert_eq!(config.audio_codec, Some(AudioCodec::Flac));
+
+        let bad = serde_json::from_value::<ScrcpyConfig>(serde_json::json!({ "device": "d", "sessionMode": "mirorr" }));
+        assert!(bad.is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use serde::Serialize;
+
+/// Connection state reported by the adb server for a device entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeviceState {
+    Device,
+    Offline,
+    Unauthorized,
+    Authorizing,
+    Connecting,
+    Recovery,
+    Rescue,
+    Sideload,
+    Bootloader,
+    NoPermissions,
+    Host,
+    Unknown,
+}
+
+impl DeviceState {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "device" => DeviceState::Device,
+            "offline" => DeviceState::Offline,
+            "unauthorized" => DeviceState::Unauthorized,
+            "authorizing" => DeviceState::Authorizing,
+            "connecting" => DeviceState::Connecting,
+            "recovery" => DeviceState::Recovery,
+            "rescue" => DeviceState::Rescue,
+            "sideload" => DeviceState::Sideload,
+            "bootloader" => DeviceState::Bootloader,
+            "host" => DeviceState::Host,
+            s if s.starts_with("no permissions") => DeviceState::NoPermissions,
+            _ => DeviceState::Unknown,
+        }
+    }
+
+    /// Short user-facing explanation for states where the device can't be used yet.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            DeviceState::Device | DeviceState::Host => None,
+            DeviceState::Unauthorized => Some("Accept the RSA debugging prompt on your phone"),
+            DeviceState::Authorizing => Some("Waiting for the device to finish authorization"),
+            DeviceState::Connecting => Some("ADB is still connecting to the device"),
+            DeviceState::Offline => Some("Device is offline, try reconnecting the cable or restarting ADB"),
+            DeviceState::NoPermissions => Some("Missing USB permissions, check your udev rules"),
+            DeviceState::Recovery | DeviceState::Rescue => Some("Device is in recovery mode"),
+            DeviceState::Sideload => Some("Device is waiting for an OTA sideload"),
+            DeviceState::Bootloader => Some("Device is in bootloader/fastboot mode"),
+            DeviceState::Unknown => Some("Unknown device state"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Usb,
+    Tcp,
+    Emulator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    pub serial: String,
+    pub state: DeviceState,
+    pub model: Option<String>,
+    pub product: Option<String>,
+    pub device: Option<String>,
+    pub transport_id: Option<u32>,
+    pub transport: Transport,
+    pub hint: Option<&'static str>,
+}
+
+fn transport_for(serial: &str) -> Transport {
+    if serial.starts_with("emulator-") {
+        Transport::Emulator
+    } else if serial.contains(':') || serial.contains("._tcp") {
+        Transport::Tcp
+    } else {
+        Transport::Usb
+    }
+}
+
+/// Parses a single `adb devices -l` line, e.g.
+/// `R58M123ABC  device usb:1-1 product:a52q model:SM_A525F device:a52q transport_id:2`.
+pub fn parse_device_line(line: &str) -> Option<Device> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("List of devices") || line.starts_with('*') {
+        return None;
+    }
+
+    let (serial, rest) = line.split_once(char::is_whitespace)?;
+    let rest = rest.trim_start();
+
+    // "no permissions (...)" spans several words, everything else is a single token
+    let (state_str, attrs) = if rest.starts_with("no permissions") {
+        let end = rest.find(']').map(|i| i + 1).unwrap_or(rest.len());
+        rest.split_at(end)
+    } else {
+        rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+    };
+
+    let state = DeviceState::parse(state_str.trim());
+    let mut device = Device {
+        serial: serial.to_string(),
+        state,
+        model: None,
+        product: None,
+        device: None,
+        transport_id: None,
+        transport: transport_for(serial),
+        hint: state.hint(),
+    };
+
+    for attr in attrs.split_whitespace() {
+        match attr.split_once(':') {
+            Some(("model", v)) => device.model = Some(v.to_string()),
+            Some(("product", v)) => device.product = Some(v.to_string()),
+            Some(("device", v)) => device.device = Some(v.to_string()),
+            Some(("transport_id", v)) => device.transport_id = v.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Some(device)
+}
+
+/// Parses the full `adb devices -l` listing. mDNS service entries are skipped since
+/// the same device is also reported under its `ip:port` serial.
+pub fn parse_device_list(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .filter_map(parse_device_line)
+        .filter(|d| !d.serial.contains("._tcp") && !d.serial.contains("._udp"))
+        .collect()
+}
+
+/// A change between two consecutive device listings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+    Added(Device),
+    Removed(Device),
+    StateChanged { device: Device, previous: DeviceState },
+}
+
+/// Compares two device listings by serial and reports what changed.
+pub fn diff_devices(previous: &[Device], current: &[Device]) -> Vec<DeviceChange> {
+    let mut changes = Vec::new();
+
+    for device in current {
+        match previous.iter().find(|p| p.serial == device.serial) {
+            None => changes.push(DeviceChange::Added(device.clone())),
+            Some(p) if p.state != device.state => changes.push(DeviceChange::StateChanged {
+                device: device.clone(),
+                previous: p.state,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for device in previous {
+        if !current.iter().any(|c| c.serial == device.serial) {
+            changes.push(DeviceChange::Removed(device.clone()));
+        }
+    }
+
+    changes
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryInfo {
+    pub level: Option<u32>,
+    pub temperature_celsius: Option<f32>,
+    pub charging: bool,
+}
+
+/// Config values that suit the device, used to prefill `ScrcpyConfig`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedDefaults {
+    pub res: String,
+    pub vd_dpi: u32,
+    pub codec: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub serial: String,
+    pub android_version: Option<String>,
+    pub sdk_level: Option<u32>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub abis: Vec<String>,
+    pub screen_width: Option<u32>,
+    pub screen_height: Option<u32>,
+    pub density: Option<u32>,
+    pub battery: Option<BatteryInfo>,
+    pub screen_off_timeout_ms: Option<u64>,
+    pub suggested: SuggestedDefaults,
+}
+
+/// Parses `getprop` output lines of the form `[ro.build.version.sdk]: [34]`.
+pub fn parse_getprop(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once("]: [")?;
+            let key = key.strip_prefix('[')?;
+            let value = value.strip_suffix(']')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the value of a `wm size` / `wm density` style output, preferring the override.
+fn parse_wm_value<'a>(output: &'a str, label: &str) -> Option<&'a str> {
+    let find = |prefix: &str| {
+        output.lines().find_map(|l| l.trim().strip_prefix(prefix).map(|v| v.trim()))
+    };
+    find(&format!("Override {}:", label)).or_else(|| find(&format!("Physical {}:", label)))
+}
+
+/// Parses `wm size` output into (width, height).
+pub fn parse_wm_size(output: &str) -> Option<(u32, u32)> {
+    let (w, h) = parse_wm_value(output, "size")?.split_once('x')?;
+    Some((w.parse().ok()?, h.parse().ok()?))
+}
+
+/// Parses `wm density` output.
+pub fn parse_wm_density(output: &str) -> Option<u32> {
+    parse_wm_value(output, "density")?.parse().ok()
+}
+
+/// Parses `dumpsys battery`. Temperature is reported in tenths of a degree.
+pub fn parse_battery(output: &str) -> Option<BatteryInfo> {
+    let fields: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|l| l.trim().split_once(": "))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+
+    // BatteryManager.BATTERY_STATUS_CHARGING = 2, BATTERY_STATUS_FULL = 5
+    let charging = matches!(fields.get("status").copied(), Some("2") | Some("5"))
+        || ["AC powered", "USB powered", "Wireless powered"]
+            .iter()
+            .any(|k| fields.get(k).copied() == Some("true"));
+
+    Some(BatteryInfo {
+        level: fields.get("level").and_then(|v| v.parse().ok()),
+        temperature_celsius: fields.get("temperature").and_then(|v| v.parse::<f32>().ok()).map(|t| t / 10.0),
+        charging,
+    })
+}
+
+pub fn suggest_defaults(width: Option<u32>, height: Option<u32>, density: Option<u32>, sdk_level: Option<u32>) -> SuggestedDefaults {
+    let longest = width.zip(height).map(|(w, h)| w.max(h)).unwrap_or(0);
+    SuggestedDefaults {
+        // Downscale anything above 1080p so encoding keeps up on most phones
+        res: if longest > 1920 { "1920".to_string() } else { "0".to_string() },
+        vd_dpi: density.unwrap_or(420),
+        // H.265 hardware encoders are reliable from Android 10 onwards
+        codec: if sdk_level.unwrap_or(0) >= 29 { "h265".to_string() } else { "h264".to_string() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_list_states() {
+        let output = "List of devices attached\n\
+            emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1\n\
+            R58M123ABC             unauthorized usb:1-1 transport_id:2\n\
+            192.168.1.5:5555       offline transport_id:3\n\
+            0123456789ABCDEF       no permissions (missing udev rules? user is in the plugdev group); see [http://developer.android.com/tools/device.html] usb:1-2 transport_id:4\n\
+            adb-R58M123ABC-xyz._adb-tls-connect._tcp device product:a52q model:SM_A525F device:a52q transport_id:5\n\
+            \n";
+
+        let devices = parse_device_list(output);
+        assert_eq!(devices.len(), 4);
+
+        assert_eq!(devices[0].serial, "emulator-5554");
+        assert_eq!(devices[0].state, DeviceState::Device);
+        assert_eq!(devices[0].transport, Transport::Emulator);
+        assert_eq!(devices[0].model.as_deref(), Some("sdk_gphone64_x86_64"));
+        assert_eq!(devices[0].device.as_deref(), Some("emu64xa"));
+        assert_eq!(devices[0].transport_id, Some(1));
+        assert!(devices[0].hint.is_none());
+
+        assert_eq!(devices[1].state, DeviceState::Unauthorized);
+        assert_eq!(devices[1].transport, Transport::Usb);
+        assert!(devices[1].hint.is_some());
+
+        assert_eq!(devices[2].state, DeviceState::Offline);
+        assert_eq!(devices[2].transport, Transport::Tcp);
+
+        assert_eq!(devices[3].state, DeviceState::NoPermissions);
+        assert_eq!(devices[3].transport_id, Some(4));
+    }
+
+    #[test]
+    fn test_diff_devices() {
+        let before = parse_device_list("R58M123ABC unauthorized usb:1-1 transport_id:2\nemulator-5554 device transport_id:1\n");
+        let after = parse_device_list("R58M123ABC device usb:1-1 model:SM_A525F transport_id:2\n192.168.1.5:5555 device transport_id:3\n");
+
+        let changes = diff_devices(&before, &after);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(&changes[0], DeviceChange::StateChanged { device, previous: DeviceState::Unauthorized } if device.serial == "R58M123ABC"));
+        assert!(matches!(&changes[1], DeviceChange::Added(d) if d.serial == "192.168.1.5:5555"));
+        assert!(matches!(&changes[2], DeviceChange::Removed(d) if d.serial == "emulator-5554"));
+    }
+
+    #[test]
+    fn test_parse_device_info_sources() {
+        let props = parse_getprop("[ro.build.version.release]: [14]\n[ro.build.version.sdk]: [34]\n[ro.product.cpu.abilist]: [arm64-v8a,armeabi-v7a]\n");
+        assert_eq!(props.get("ro.build.version.sdk").map(String::as_str), Some("34"));
+        assert_eq!(props.get("ro.product.cpu.abilist").map(String::as_str), Some("arm64-v8a,armeabi-v7a"));
+
+        assert_eq!(parse_wm_size("Physical size: 1440x3120\n"), Some((1440, 3120)));
+        assert_eq!(parse_wm_size("Physical size: 1440x3120\nOverride size: 1080x2340\n"), Some((1080, 2340)));
+        assert_eq!(parse_wm_density("Physical density: 560\nOverride density: 480\n"), Some(480));
+
+        let battery = parse_battery("Current Battery Service state:\n  AC powered: false\n  USB powered: true\n  status: 2\n  level: 85\n  temperature: 312\n").unwrap();
+        assert_eq!(battery.level, Some(85));
+        assert_eq!(battery.temperature_celsius, Some(31.2));
+        assert!(battery.charging);
+
+        let suggested = suggest_defaults(Some(1440), Some(3120), Some(560), Some(34));
+        assert_eq!(suggested.res, "1920");
+        assert_eq!(suggested.vd_dpi, 560);
+        assert_eq!(suggested.codec, "h265");
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Read;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::adb::{remote_join, AdbClient, AdbError};
+use crate::transfer::TransferSummary;
+
+/// How `sync_dir` decides whether a file that exists on both sides changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompareMode {
+    /// Size or modification time differ. Pushes keep the local mtime, so a
+    /// synced file compares equal afterwards.
+    #[default]
+    SizeMtime,
+    /// Size or SHA-256 differ, for when mtimes can't be trusted.
+    Hash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMeta {
+    pub size: u64,
+    pub mtime: u64,
+}
+
+/// Files and directories under a root, keyed by `/`-separated relative path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tree {
+    pub files: BTreeMap<String, FileMeta>,
+    pub dirs: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushReason {
+    New,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum SyncAction {
+    #[serde(rename_all = "camelCase")]
+    Push { path: String, size: u64, reason: PushReason },
+    /// Removes a file, or a whole directory missing locally.
+    #[serde(rename_all = "camelCase")]
+    Delete { path: String, is_dir: bool },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+    pub actions: Vec<SyncAction>,
+    pub unchanged: u32,
+    pub bytes_to_push: u64,
+}
+
+/// Result of `sync_dir`. `summary` is `None` for dry runs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub plan: SyncPlan,
+    pub dry_run: bool,
+    pub summary: Option<TransferSummary>,
+}
+
+pub fn scan_local(root: &Path) -> std::io::Result<Tree> {
+    let mut tree = Tree::default();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let metadata = entry.metadata()?;
+
+            if metadata.is_dir() {
+                tree.dirs.insert(rel.clone());
+                pending.push((entry.path(), rel));
+            } else if metadata.is_file() {
+                let mtime = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                tree.files.insert(rel, FileMeta { size: metadata.len(), mtime });
+            }
+        }
+    }
+    Ok(tree)
+}
+
+/// Walks a device directory through the sync service. A missing root is an empty tree.
+pub async fn scan_remote(client: &AdbClient, serial: &str, root: &str) -> Result<Tree, AdbError> {
+    let mut tree = Tree::default();
+    let mut pending = vec![(root.to_string(), String::new())];
+
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in client.list_dir(serial, &dir).await? {
+            let rel = if prefix.is_empty() { entry.name.clone() } else { format!("{}/{}", prefix, entry.name) };
+            if entry.stat.is_dir() {
+                tree.dirs.insert(rel.clone());
+                pending.push((remote_join(&dir, &entry.name), rel));
+            } else if entry.stat.is_file() {
+                tree.files.insert(rel, FileMeta { size: entry.stat.size, mtime: entry.stat.mtime });
+            }
+        }
+    }
+    Ok(tree)
+}
+
+/// Files present on both sides with the same size, the only ones worth hashing.
+pub fn hash_candidates(local: &Tree, remote: &Tree) -> Vec<String> {
+    local
+        .files
+        .iter()
+        .filter(|(path, meta)| remote.files.get(*path).is_some_and(|r| r.size == meta.size))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Works out what to push and delete. `hash_mismatches` lists the files whose
+/// contents differ when comparing by hash, it's ignored otherwise.
+pub fn plan(local: &Tree, remote: &Tree, mode: CompareMode, hash_mismatches: &BTreeSet<String>, delete_extras: bool) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+
+    for (path, meta) in &local.files {
+        let reason = match remote.files.get(path) {
+            None => Some(PushReason::New),
+            Some(r) if r.size != meta.size => Some(PushReason::Changed),
+            Some(r) => {
+                let changed = match mode {
+                    CompareMode::SizeMtime => r.mtime != meta.mtime,
+                    CompareMode::Hash => hash_mismatches.contains(path),
+                };
+                changed.then_some(PushReason::Changed)
+            }
+        };
+
+        match reason {
+            Some(reason) => {
+                plan.bytes_to_push += meta.size;
+                plan.actions.push(SyncAction::Push { path: path.clone(), size: meta.size, reason });
+            }
+            None => plan.unchanged += 1,
+        }
+    }
+
+    if delete_extras {
+        // Only the topmost extra directory is deleted, it takes its contents with it
+        let extra_dirs: Vec<&String> = remote.dirs.iter().filter(|d| !local.dirs.contains(*d)).collect();
+        let inside_extra = |path: &str| extra_dirs.iter().any(|d| path.starts_with(&format!("{}/", d)));
+
+        for dir in &extra_dirs {
+            if !inside_extra(dir) {
+                plan.actions.push(SyncAction::Delete { path: (*dir).clone(), is_dir: true });
+            }
+        }
+        for path in remote.files.keys() {
+            if !local.files.contains_key(path) && !inside_extra(path) {
+                plan.actions.push(SyncAction::Delete { path: path.clone(), is_dir: false });
+            }
+        }
+    }
+    plan
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Parses `sha256sum` output (`<hash>  <path>`) into path -> hash.
+pub fn parse_sha256sum(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (hash, path) = line.trim_end_matches('\r').split_once("  ")?;
+            (hash.len() == 64).then(|| (path.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(files: &[(&str, u64, u64)], dirs: &[&str]) -> Tree {
+        Tree {
+            files: files.iter().map(|(p, size, mtime)| (p.to_string(), FileMeta { size: *size, mtime: *mtime })).collect(),
+            dirs: dirs.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_plan_size_mtime() {
+        let local = tree(&[("a.mp4", 10, 100), ("clips/b.mp4", 20, 200), ("c.jpg", 5, 300)], &["clips"]);
+        let remote = tree(
+            &[("a.mp4", 10, 100), ("clips/b.mp4", 20, 150), ("old.txt", 1, 1), ("stale/x.bin", 3, 1)],
+            &["clips", "stale"],
+        );
+
+        let plan = plan(&local, &remote, CompareMode::SizeMtime, &BTreeSet::new(), true);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.bytes_to_push, 25);
+        assert_eq!(plan.actions, vec![
+            SyncAction::Push { path: "c.jpg".to_string(), size: 5, reason: PushReason::New },
+            SyncAction::Push { path: "clips/b.mp4".to_string(), size: 20, reason: PushReason::Changed },
+            SyncAction::Delete { path: "stale".to_string(), is_dir: true },
+            SyncAction::Delete { path: "old.txt".to_string(), is_dir: false },
+        ]);
+
+        let keep = super::plan(&local, &remote, CompareMode::SizeMtime, &BTreeSet::new(), false);
+        assert!(keep.actions.iter().all(|a| matches!(a, SyncAction::Push { .. })));
+    }
+
+    #[test]
+    fn test_plan_hash() {
+        let local = tree(&[("a.mp4", 10, 100), ("b.mp4", 10, 100)], &[]);
+        let remote = tree(&[("a.mp4", 10, 999), ("b.mp4", 10, 999)], &[]);
+        assert_eq!(hash_candidates(&local, &remote), vec!["a.mp4", "b.mp4"]);
+
+        let mismatches: BTreeSet<String> = ["b.mp4".to_string()].into();
+        let plan = plan(&local, &remote, CompareMode::Hash, &mismatches, false);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.actions, vec![SyncAction::Push { path: "b.mp4".to_string(), size: 10, reason: PushReason::Changed }]);
+    }
+
+    #[test]
+    fn test_plan_large_files() {
+        let size = u32::MAX as u64 + 1024;
+        let local = tree(&[("qa/capture.mkv", size, 100)], &["qa"]);
+        let remote = tree(&[("qa/capture.mkv", size, 100)], &["qa"]);
+        let plan = plan(&local, &remote, CompareMode::SizeMtime, &BTreeSet::new(), true);
+        assert_eq!((plan.unchanged, plan.bytes_to_push), (1, 0));
+        assert!(plan.actions.is_empty());
+
+        // What a 32-bit listing reports for the same file
+        let truncated = tree(&[("qa/capture.mkv", size & 0xffff_ffff, 100)], &["qa"]);
+        let replan = super::plan(&local, &truncated, CompareMode::SizeMtime, &BTreeSet::new(), true);
+        assert_eq!(replan.bytes_to_push, size);
+    }
+
+    #[test]
+    fn test_local_scan_and_hash() {
+        let root = std::env::temp_dir().join(format!("scrcpy_gui_sync_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("clips")).unwrap();
+        std::fs::write(root.join("clips").join("b.txt"), b"abc").unwrap();
+
+        let tree = scan_local(&root).unwrap();
+        assert!(tree.dirs.contains("clips"));
+        assert_eq!(tree.files["clips/b.txt"].size, 3);
+        assert_eq!(
+            sha256_file(&root.join("clips").join("b.txt")).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let _ = std::fs::remove_dir_all(&root);
+
+        let sums = parse_sha256sum("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  clips/b.txt\n");
+        assert_eq!(sums["clips/b.txt"].len(), 64);
+    }
+}
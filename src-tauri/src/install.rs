@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+
+/// Options of `install_apk`, mapped to `adb install` / `pm install` flags.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InstallOptions {
+    /// `-r`, replace an installed app keeping its data.
+    pub replace: bool,
+    /// `-d`, allow a lower version code.
+    pub downgrade: bool,
+    /// `-g`, grant every runtime permission in the manifest.
+    pub grant_permissions: bool,
+    /// `-t`, allow APKs marked `android:testOnly`.
+    pub test_only: bool,
+    /// `--user`, a user id, `current` or `all`.
+    pub user: Option<String>,
+    /// `--incremental`, streams the APK while the app starts (Android 11+).
+    /// Only `adb install` supports it, so it bypasses the native install.
+    pub incremental: bool,
+}
+
+impl InstallOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.user.as_deref() {
+            Some(user) if user != "current" && user != "all" && user.parse::<u32>().is_err() => {
+                Err(format!("Invalid user: {} (expected a user id, current or all)", user))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Flags shared by `pm install` and `adb install`/`install-multiple`.
+    pub fn pm_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        for (enabled, flag) in [(self.replace, "-r"), (self.downgrade, "-d"), (self.grant_permissions, "-g"), (self.test_only, "-t")] {
+            if enabled {
+                flags.push(flag.to_string());
+            }
+        }
+        if let Some(user) = &self.user {
+            flags.push("--user".to_string());
+            flags.push(user.clone());
+        }
+        flags
+    }
+
+    /// Flags for the adb client's install commands.
+    pub fn adb_flags(&self) -> Vec<String> {
+        let mut flags = self.pm_flags();
+        if self.incremental {
+            flags.push("--incremental".to_string());
+        }
+        flags
+    }
+}
+
+const CODES: [(&str, InstallFailure); 34] = [
+    ("INSTALL_FAILED_ALREADY_EXISTS", InstallFailure::AlreadyExists),
+    ("INSTALL_FAILED_INVALID_APK", InstallFailure::InvalidApk),
+    ("INSTALL_FAILED_INSUFFICIENT_STORAGE", InstallFailure::InsufficientStorage),
+    ("INSTALL_FAILED_DUPLICATE_PACKAGE", InstallFailure::DuplicatePackage),
+    ("INSTALL_FAILED_UPDATE_INCOMPATIBLE", InstallFailure::UpdateIncompatible),
+    ("INSTALL_FAILED_VERSION_DOWNGRADE", InstallFailure::VersionDowngrade),
+    ("INSTALL_FAILED_SHARED_USER_INCOMPATIBLE", InstallFailure::SharedUserIncompatible),
+    ("INSTALL_FAILED_MISSING_SHARED_LIBRARY", InstallFailure::MissingSharedLibrary),
+    ("INSTALL_FAILED_OLDER_SDK", InstallFailure::OlderSdk),
+    ("INSTALL_FAILED_NEWER_SDK", InstallFailure::NewerSdk),
+    ("INSTALL_FAILED_DEPRECATED_SDK_VERSION", InstallFailure::DeprecatedSdkVersion),
+    ("INSTALL_FAILED_CONFLICTING_PROVIDER", InstallFailure::ConflictingProvider),
+    ("INSTALL_FAILED_DUPLICATE_PERMISSION", InstallFailure::DuplicatePermission),
+    ("INSTALL_FAILED_TEST_ONLY", InstallFailure::TestOnly),
+    ("INSTALL_FAILED_CPU_ABI_INCOMPATIBLE", InstallFailure::CpuAbiIncompatible),
+    ("INSTALL_FAILED_NO_MATCHING_ABIS", InstallFailure::NoMatchingAbis),
+    ("INSTALL_FAILED_MISSING_FEATURE", InstallFailure::MissingFeature),
+    ("INSTALL_FAILED_MISSING_SPLIT", InstallFailure::MissingSplit),
+    ("INSTALL_FAILED_INVALID_INSTALL_LOCATION", InstallFailure::InvalidInstallLocation),
+    ("INSTALL_FAILED_VERIFICATION_FAILURE", InstallFailure::VerificationFailure),
+    ("INSTALL_FAILED_VERIFICATION_TIMEOUT", InstallFailure::VerificationTimeout),
+    ("INSTALL_FAILED_USER_RESTRICTED", InstallFailure::UserRestricted),
+    ("INSTALL_FAILED_PERMISSION_MODEL_DOWNGRADE", InstallFailure::PermissionModelDowngrade),
+    ("INSTALL_FAILED_ABORTED", InstallFailure::Aborted),
+    ("INSTALL_FAILED_INTERNAL_ERROR", InstallFailure::InternalError),
+    ("INSTALL_PARSE_FAILED_NOT_APK", InstallFailure::NotApk),
+    ("INSTALL_PARSE_FAILED_BAD_MANIFEST", InstallFailure::BadManifest),
+    ("INSTALL_PARSE_FAILED_NO_CERTIFICATES", InstallFailure::NoCertificates),
+    ("INSTALL_PARSE_FAILED_INCONSISTENT_CERTIFICATES", InstallFailure::InconsistentCertificates),
+    ("INSTALL_PARSE_FAILED_CERTIFICATE_ENCODING", InstallFailure::CertificateEncoding),
+    ("INSTALL_PARSE_FAILED_BAD_PACKAGE_NAME", InstallFailure::BadPackageName),
+    ("INSTALL_PARSE_FAILED_MANIFEST_MALFORMED", InstallFailure::ManifestMalformed),
+    ("INSTALL_PARSE_FAILED_MANIFEST_EMPTY", InstallFailure::ManifestEmpty),
+    ("INSTALL_PARSE_FAILED_UNEXPECTED_EXCEPTION", InstallFailure::ParseUnexpectedException),
+];
+
+/// Package manager install failures, from the `INSTALL_FAILED_*` and
+/// `INSTALL_PARSE_FAILED_*` codes in its output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallFailure {
+    AlreadyExists,
+    InvalidApk,
+    InsufficientStorage,
+    DuplicatePackage,
+    UpdateIncompatible,
+    VersionDowngrade,
+    SharedUserIncompatible,
+    MissingSharedLibrary,
+    OlderSdk,
+    NewerSdk,
+    DeprecatedSdkVersion,
+    ConflictingProvider,
+    DuplicatePermission,
+    TestOnly,
+    CpuAbiIncompatible,
+    NoMatchingAbis,
+    MissingFeature,
+    MissingSplit,
+    InvalidInstallLocation,
+    VerificationFailure,
+    VerificationTimeout,
+    UserRestricted,
+    PermissionModelDowngrade,
+    Aborted,
+    InternalError,
+    NotApk,
+    BadManifest,
+    NoCertificates,
+    InconsistentCertificates,
+    CertificateEncoding,
+    BadPackageName,
+    ManifestMalformed,
+    ManifestEmpty,
+    ParseUnexpectedException,
+    /// A code without a dedicated explanation, kept as printed.
+    Other(String),
+}
+
+impl InstallFailure {
+    pub fn parse(code: &str) -> Self {
+        CODES
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, failure)| failure.clone())
+            .unwrap_or_else(|| InstallFailure::Other(code.to_string()))
+    }
+
+    /// Finds the failure code in `adb install` or `pm install` output, e.g.
+    /// `adb: failed to install app.apk: Failure [INSTALL_FAILED_TEST_ONLY: installPackageLI]`.
+    pub fn from_output(output: &str) -> Option<Self> {
+        let start = ["INSTALL_FAILED_", "INSTALL_PARSE_FAILED_"].iter().filter_map(|p| output.find(p)).min()?;
+        let code: String = output[start..].chars().take_while(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || *c == '_').collect();
+        Some(Self::parse(&code))
+    }
+
+    /// Explanation and suggested fix.
+    pub fn describe(&self) -> (&'static str, &'static str) {
+        match self {
+            InstallFailure::AlreadyExists => ("The app is already installed", "Enable the replace option (-r)"),
+            InstallFailure::InvalidApk | InstallFailure::NotApk => {
+                ("The file isn't a valid APK", "Check the download finished, or rebuild the APK")
+            }
+            InstallFailure::InsufficientStorage => ("Not enough free storage on the device", "Free up space on the device and try again"),
+            InstallFailure::DuplicatePackage => ("A package with the same name is already being installed", "Wait a moment and try again"),
+            InstallFailure::UpdateIncompatible | InstallFailure::InconsistentCertificates => (
+                "The installed app is signed with a different key",
+                "Uninstall the existing app first (this deletes its data)",
+            ),
+            InstallFailure::VersionDowngrade => (
+                "The APK has a lower version code than the installed app",
+                "Enable the downgrade option (-d), or uninstall the existing app first",
+            ),
+            InstallFailure::SharedUserIncompatible => (
+                "The APK requests a shared user id signed with a different key",
+                "Uninstall the apps sharing that user id, or sign the APK with the same key",
+            ),
+            InstallFailure::MissingSharedLibrary => (
+                "The APK needs a shared library the device doesn't have",
+                "Install the required library first, or use a build without it",
+            ),
+            InstallFailure::OlderSdk => ("The device's Android version is below the app's minSdkVersion", "Use a device with a newer Android version, or lower minSdkVersion"),
+            InstallFailure::NewerSdk => ("The device's Android version is above the app's maxSdkVersion", "Use an older device, or raise maxSdkVersion"),
+            InstallFailure::DeprecatedSdkVersion => (
+                "The app targets an SDK too old for this Android version",
+                "Raise targetSdkVersion, or install with `--bypass-low-target-sdk-block` from a terminal",
+            ),
+            InstallFailure::ConflictingProvider => (
+                "A content provider authority is already used by another installed app",
+                "Uninstall the other app, or change the provider authority",
+            ),
+            InstallFailure::DuplicatePermission => (
+                "A custom permission is already defined by another installed app",
+                "Uninstall the other app, or rename the permission",
+            ),
+            InstallFailure::TestOnly => ("The APK is marked as test-only", "Enable the test-only option (-t)"),
+            InstallFailure::CpuAbiIncompatible | InstallFailure::NoMatchingAbis => (
+                "The APK has no native libraries for this device's CPU",
+                "Use a build for the device's ABI, or a universal APK",
+            ),
+            InstallFailure::MissingFeature => ("The app requires a hardware or software feature the device lacks", "Use a different device"),
+            InstallFailure::MissingSplit => ("A required split APK is missing", "Install the whole split set (.apks/.xapk) instead of a single APK"),
+            InstallFailure::InvalidInstallLocation => ("The requested install location can't be used", "Install to internal storage"),
+            InstallFailure::VerificationFailure => (
+                "Package verification rejected the APK",
+                "Disable \"Verify apps over USB\" in developer options, or check Play Protect",
+            ),
+            InstallFailure::VerificationTimeout => ("Package verification timed out", "Try again, or disable \"Verify apps over USB\""),
+            InstallFailure::UserRestricted => (
+                "Installing apps is restricted on this device or user",
+                "Enable \"Install via USB\" in developer options (MIUI/ColorOS), or lift the device policy",
+            ),
+            InstallFailure::PermissionModelDowngrade => (
+                "The new APK targets a pre-runtime-permissions SDK while the installed one doesn't",
+                "Uninstall the existing app first",
+            ),
+            InstallFailure::Aborted => ("The install was cancelled on the device", "Accept the install prompt on the device"),
+            InstallFailure::InternalError => ("The package manager hit an internal error", "Check logcat for details and try again"),
+            InstallFailure::BadManifest | InstallFailure::ManifestMalformed | InstallFailure::ManifestEmpty => {
+                ("The APK's manifest is invalid", "Fix the AndroidManifest.xml and rebuild")
+            }
+            InstallFailure::NoCertificates | InstallFailure::CertificateEncoding => {
+                ("The APK isn't signed properly", "Sign the APK (apksigner) and try again")
+            }
+            InstallFailure::BadPackageName => ("The package name is invalid", "Fix the applicationId and rebuild"),
+            InstallFailure::ParseUnexpectedException => ("The package manager couldn't parse the APK", "Check the APK isn't corrupted"),
+            InstallFailure::Other(_) => ("The package manager refused the install", "Check the full message for details"),
+        }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            InstallFailure::Other(code) => code.clone(),
+            known => CODES.iter().find(|(_, f)| f == known).map(|(c, _)| c.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Failure details returned by `install_apk` next to the raw message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallError {
+    pub code: String,
+    pub explanation: &'static str,
+    pub fix: &'static str,
+}
+
+impl From<InstallFailure> for InstallError {
+    fn from(failure: InstallFailure) -> Self {
+        let (explanation, fix) = failure.describe();
+        Self { code: failure.code(), explanation, fix }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_flags() {
+        let options = InstallOptions { replace: true, grant_permissions: true, user: Some("10".to_string()), incremental: true, ..Default::default() };
+        assert_eq!(options.pm_flags(), vec!["-r", "-g", "--user", "10"]);
+        assert_eq!(options.adb_flags().last().map(String::as_str), Some("--incremental"));
+        assert!(options.validate().is_ok());
+        assert!(InstallOptions { user: Some("10; reboot".to_string()), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_failure_from_output() {
+        let output = "Performing Streamed Install\nadb: failed to install app.apk: Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: Update version code 3 is older than current 5]";
+        assert_eq!(InstallFailure::from_output(output), Some(InstallFailure::VersionDowngrade));
+
+        let error = InstallError::from(InstallFailure::from_output("Failure [INSTALL_PARSE_FAILED_NO_CERTIFICATES: Failed to collect certificates]").unwrap());
+        assert_eq!(error.code, "INSTALL_PARSE_FAILED_NO_CERTIFICATES");
+
+        let unknown = InstallFailure::from_output("Failure [INSTALL_FAILED_SESSION_INVALID]").unwrap();
+        assert_eq!(unknown.code(), "INSTALL_FAILED_SESSION_INVALID");
+        assert_eq!(InstallFailure::from_output("Success"), None);
+    }
+
+    #[test]
+    fn test_known_codes_round_trip() {
+        for (code, _) in CODES {
+            assert_eq!(InstallFailure::parse(code).code(), code);
+        }
+    }
+}
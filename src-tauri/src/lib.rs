@@ -1,11 +1,43 @@
+mod adb;
+mod apk_bundle;
 mod commands;
+mod config;
+mod device;
+mod dir_sync;
+mod install;
+mod packages;
+mod processes;
+mod profiles;
+mod recording;
+mod remote_files;
+mod scrcpy_log;
+mod scrcpy_options;
+mod session;
+mod transfer;
+mod transfer_queue;
 use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
 use std::sync::Mutex;
-use tauri::Manager;
-use tokio::process::Child;
+use tauri::{Manager, RunEvent};
+use tokio::task::JoinHandle;
+use session::{ScrcpySession, DEFAULT_STOP_GRACE};
 
 pub struct ScrcpyState {
-    pub processes: Mutex<HashMap<String, Child>>,
+    /// Running sessions keyed by session id, so one device can host several at once.
+    pub processes: Mutex<HashMap<String, ScrcpySession>>,
+    pub next_session_id: AtomicU64,
+}
+
+pub struct TransferState {
+    pub transfers: Mutex<transfer::TransferRegistry>,
+}
+
+pub struct TransferQueueState {
+    pub queue: Mutex<transfer_queue::TransferQueue>,
+}
+
+pub struct DeviceWatcherState {
+    pub task: Mutex<Option<JoinHandle<()>>>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -24,7 +56,18 @@ pub fn run() {
         .setup(|app| {
             app.manage(ScrcpyState {
                 processes: Mutex::new(HashMap::new()),
+                next_session_id: AtomicU64::new(1),
+            });
+            app.manage(TransferState {
+                transfers: Mutex::new(transfer::TransferRegistry::default()),
+            });
+            app.manage(TransferQueueState {
+                queue: Mutex::new(transfer_queue::TransferQueue::default()),
+            });
+            app.manage(DeviceWatcherState {
+                task: Mutex::new(None),
             });
+            commands::load_adb_server(app.handle());
 
             Ok(())
         })
@@ -32,21 +75,69 @@ pub fn run() {
             commands::greet,
             commands::check_scrcpy,
             commands::get_devices,
+            commands::start_device_watcher,
+            commands::stop_device_watcher,
             commands::adb_connect,
             commands::get_mdns_devices,
             commands::adb_pair,
             commands::adb_shell,
+            commands::get_device_info,
             commands::push_file,
+            commands::pull_file,
+            commands::pull_dir,
+            commands::list_remote_dir,
+            commands::delete_remote,
+            commands::mkdir_remote,
+            commands::rename_remote,
+            commands::sync_dir,
             commands::install_apk,
+            commands::list_packages,
+            commands::uninstall_package,
+            commands::clear_app_data,
+            commands::force_stop_app,
+            commands::set_package_enabled,
+            commands::launch_app,
+            commands::backup_apk,
+            commands::cancel_transfer,
+            commands::enqueue_push,
+            commands::list_transfer_queue,
+            commands::set_queue_concurrency,
+            commands::cancel_queue_item,
+            commands::clear_finished_transfers,
             commands::kill_adb,
+            commands::get_adb_server,
+            commands::set_adb_server,
             commands::run_scrcpy,
             commands::stop_scrcpy,
+            commands::stop_all_sessions,
+            commands::list_sessions,
+            commands::list_profiles,
+            commands::save_profile,
+            commands::load_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::import_profile,
+            commands::export_profile,
+            commands::get_device_profiles,
+            commands::set_device_profile,
             commands::download_scrcpy,
             commands::list_scrcpy_options,
+            commands::list_encoders,
+            commands::list_displays,
+            commands::list_cameras,
+            commands::list_camera_sizes,
+            commands::list_apps,
             commands::get_videos_dir,
             commands::save_report,
             commands::run_terminal_command
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            // Don't leave scrcpy windows (and their device-side servers) behind
+            if let RunEvent::Exit = event {
+                let state = app_handle.state::<ScrcpyState>();
+                tauri::async_runtime::block_on(commands::stop_all(&state, DEFAULT_STOP_GRACE));
+            }
+        });
 }
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+/// Which packages `list_packages` returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageFilter {
+    #[default]
+    All,
+    /// Third party apps.
+    User,
+    System,
+    Disabled,
+    Enabled,
+}
+
+impl PackageFilter {
+    /// The matching `pm list packages` flag.
+    pub fn pm_flag(self) -> Option<&'static str> {
+        match self {
+            PackageFilter::All => None,
+            PackageFilter::User => Some("-3"),
+            PackageFilter::System => Some("-s"),
+            PackageFilter::Disabled => Some("-d"),
+            PackageFilter::Enabled => Some("-e"),
+        }
+    }
+}
+
+/// An installed package, as returned by `list_packages`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageInfo {
+    pub package: String,
+    /// Path of the base APK.
+    pub apk_path: String,
+    pub version_code: Option<u64>,
+    pub version_name: Option<String>,
+    pub system: bool,
+    pub enabled: bool,
+}
+
+/// Result of `backup_apk`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApkBackup {
+    pub package: String,
+    /// Pulled APKs, empty when they were bundled.
+    pub files: Vec<String>,
+    pub bytes: u64,
+    /// The `.apks` archive, when one was requested.
+    pub bundle: Option<String>,
+}
+
+/// Package names are plain Java identifiers joined with dots, anything else
+/// is refused before it reaches a device shell.
+pub fn check_package_name(package: &str) -> Result<&str, String> {
+    let package = package.trim();
+    let valid = !package.is_empty()
+        && package.split('.').all(|part| {
+            part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+    if valid { Ok(package) } else { Err(format!("Invalid package name: {}", package)) }
+}
+
+/// Parses `pm list packages -f --show-versioncode` lines, e.g.
+/// `package:/data/app/~~x==/com.example-y==/base.apk=com.example versionCode:42`,
+/// into (package, apk path, version code).
+pub fn parse_package_list(output: &str) -> Vec<(String, String, Option<u64>)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().strip_prefix("package:")?;
+            let (entry, version_code) = match line.rsplit_once(" versionCode:") {
+                Some((entry, code)) => (entry, code.trim().parse().ok()),
+                None => (line, None),
+            };
+            // The path itself may contain '=', the package name can't
+            let (path, package) = entry.rsplit_once('=').unwrap_or(("", entry));
+            Some((package.to_string(), path.to_string(), version_code))
+        })
+        .collect()
+}
+
+/// Package names from plain `pm list packages` output.
+pub fn parse_package_names(output: &str) -> HashSet<String> {
+    output.lines().filter_map(|line| line.trim().strip_prefix("package:")).map(|p| p.to_string()).collect()
+}
+
+/// Version names from `dumpsys package packages`, keyed by package.
+pub fn parse_version_names(output: &str) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Package [") {
+            current = rest.split_once(']').map(|(package, _)| package.to_string());
+        } else if let Some(version) = line.strip_prefix("versionName=") {
+            // Updated system apps list the update first, keep that one
+            if let Some(package) = current.take() {
+                names.insert(package, version.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Combines the outputs of the listing commands into `PackageInfo`s, sorted by name.
+pub fn build_package_list(
+    listing: &str,
+    system: &HashSet<String>,
+    disabled: &HashSet<String>,
+    version_names: &HashMap<String, String>,
+) -> Vec<PackageInfo> {
+    let mut packages: Vec<PackageInfo> = parse_package_list(listing)
+        .into_iter()
+        .map(|(package, apk_path, version_code)| PackageInfo {
+            system: system.contains(&package),
+            enabled: !disabled.contains(&package),
+            version_name: version_names.get(&package).cloned(),
+            package,
+            apk_path,
+            version_code,
+        })
+        .collect();
+    packages.sort_by(|a, b| a.package.cmp(&b.package));
+    packages
+}
+
+/// APK paths from `pm path`, base first, e.g. `package:/data/app/~~x==/com.example-y==/base.apk`.
+pub fn parse_pm_path(output: &str) -> Vec<String> {
+    let mut paths: Vec<String> = output.lines().filter_map(|line| line.trim().strip_prefix("package:")).map(|p| p.to_string()).collect();
+    paths.sort_by_key(|p| !p.ends_with("/base.apk"));
+    paths
+}
+
+/// `pm uninstall` and `pm clear` print `Success`, or the reason they failed.
+pub fn pm_result(output: &str) -> Result<(), String> {
+    let output = output.trim();
+    if output.lines().any(|l| l.trim() == "Success") {
+        Ok(())
+    } else if output.is_empty() {
+        Err("No output from the package manager".to_string())
+    } else {
+        Err(output.to_string())
+    }
+}
+
+/// The launcher activity from `cmd package resolve-activity --brief`, whose
+/// last line is the component (`com.example/.MainActivity`).
+pub fn parse_resolved_activity(output: &str) -> Option<String> {
+    let last = output.lines().map(str::trim).rfind(|l| !l.is_empty())?;
+    (last.contains('/') && !last.contains(' ')).then(|| last.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_list() {
+        let output = "package:/data/app/~~aB3==/com.example.app-Xy9==/base.apk=com.example.app versionCode:42\r\n\
+                      package:/system/priv-app/Settings/Settings.apk=com.android.settings versionCode:34\n";
+        let packages = parse_package_list(output);
+        assert_eq!(packages[0], ("com.example.app".to_string(), "/data/app/~~aB3==/com.example.app-Xy9==/base.apk".to_string(), Some(42)));
+        assert_eq!(packages[1].0, "com.android.settings");
+
+        let system = parse_package_names("package:com.android.settings\n");
+        let dumpsys = "Packages:\n  Package [com.example.app] (5c1b2a0):\n    versionCode=42 minSdk=24 targetSdk=34\n    versionName=1.4.2\n";
+        let list = build_package_list(output, &system, &HashSet::new(), &parse_version_names(dumpsys));
+        assert_eq!(list[0].package, "com.android.settings");
+        assert!(list[0].system);
+        assert_eq!(list[1].version_name.as_deref(), Some("1.4.2"));
+        assert!(list[1].enabled);
+    }
+
+    #[test]
+    fn test_check_package_name() {
+        assert!(check_package_name("com.example.app_2").is_ok());
+        assert!(check_package_name("com.example; reboot").is_err());
+        assert!(check_package_name("com..example").is_err());
+        assert!(check_package_name("").is_err());
+    }
+
+    #[test]
+    fn test_parse_pm_path() {
+        let output = "package:/data/app/~~x==/com.example-y==/split_config.xxhdpi.apk\npackage:/data/app/~~x==/com.example-y==/base.apk\n";
+        let paths = parse_pm_path(output);
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].ends_with("/base.apk"));
+        assert!(parse_pm_path("").is_empty());
+    }
+
+    #[test]
+    fn test_pm_output() {
+        assert!(pm_result("Success\n").is_ok());
+        assert_eq!(pm_result("Failure [DELETE_FAILED_INTERNAL_ERROR]"), Err("Failure [DELETE_FAILED_INTERNAL_ERROR]".to_string()));
+        assert_eq!(
+            parse_resolved_activity("priority=0 preferredOrder=0 match=0x108000 specificIndex=-1 isDefault=true\ncom.example.app/.MainActivity\n"),
+            Some("com.example.app/.MainActivity".to_string())
+        );
+        assert_eq!(parse_resolved_activity("No activity found\n"), None);
+    }
+}
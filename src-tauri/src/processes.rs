@@ -0,0 +1,261 @@
+use std::path::{Path, PathBuf};
+use crate::adb::DEFAULT_ADB_PORT;
+
+/// A running adb process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessEntry {
+    pub pid: u32,
+    /// Full executable path, when the OS lets us see it.
+    pub exe: Option<PathBuf>,
+    pub cmdline: String,
+    /// Server port from its environment, when the OS lets us read it (Linux).
+    pub env_port: Option<u16>,
+}
+
+impl ProcessEntry {
+    /// Port of the server this process runs or, for a client, talks to. Like
+    /// adb itself, an option on the command line beats the environment.
+    pub fn target_port(&self) -> u16 {
+        flag_port(&self.cmdline).or(self.env_port).unwrap_or(DEFAULT_ADB_PORT)
+    }
+}
+
+fn is_adb_name(name: &str) -> bool {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    name.eq_ignore_ascii_case("adb") || name.eq_ignore_ascii_case("adb.exe")
+}
+
+/// Reads the server port from an adb command line's `-L` or `-P` option, e.g.
+/// `adb -L tcp:5037 fork-server server --reply-fd 4` or `adb -P 5038 shell`.
+pub fn flag_port(cmdline: &str) -> Option<u16> {
+    let args: Vec<&str> = cmdline.split_whitespace().collect();
+    let mut port = None;
+    for pair in args.windows(2) {
+        match pair[0] {
+            "-L" => {
+                if let Some(p) = pair[1].rsplit(':').next().and_then(|p| p.parse().ok()) {
+                    port = Some(p);
+                }
+            }
+            "-P" => {
+                if let Ok(p) = pair[1].parse() {
+                    port = Some(p);
+                }
+            }
+            _ => {}
+        }
+    }
+    port
+}
+
+/// Reads the server port from a NUL-separated environment block, as in
+/// `/proc/<pid>/environ`: `ADB_SERVER_SOCKET` first, then `ANDROID_ADB_SERVER_PORT`.
+/// `create_command` sets both for a non-default server.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn env_port(environ: &str) -> Option<u16> {
+    let var = |name: &str| environ.split('\0').find_map(|entry| entry.strip_prefix(name)?.strip_prefix('='));
+    var("ADB_SERVER_SOCKET")
+        .and_then(|socket| socket.rsplit(':').next()?.parse().ok())
+        .or_else(|| var("ANDROID_ADB_SERVER_PORT")?.trim().parse().ok())
+}
+
+/// Resolves a program as `get_binary_path` returns it (a full path, or a bare
+/// name left to `PATH`) to the actual file.
+pub fn resolve_executable(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.canonicalize().ok();
+    }
+
+    let exe_ext = std::env::consts::EXE_EXTENSION;
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths).find_map(|dir| {
+            let candidate = dir.join(program).with_extension(exe_ext);
+            if candidate.is_file() { candidate.canonicalize().ok() } else { None }
+        })
+    })
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    let a = a.canonicalize().unwrap_or_else(|_| a.to_path_buf());
+    let b = b.canonicalize().unwrap_or_else(|_| b.to_path_buf());
+    if cfg!(target_os = "windows") {
+        a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+/// Pids of the processes started from `adb_exe` that belong to the server on
+/// `port`: the server itself and the clients talking to it, going by their
+/// `-P`/`-L` options or environment and the default port otherwise. Servers and
+/// clients on other ports and adb binaries from elsewhere (Android Studio, CI
+/// harnesses) are left alone.
+pub fn select_own(processes: &[ProcessEntry], adb_exe: &Path, port: u16) -> Vec<u32> {
+    processes
+        .iter()
+        .filter(|p| p.exe.as_deref().is_some_and(|exe| same_file(exe, adb_exe)))
+        .filter(|p| p.target_port() == port)
+        .map(|p| p.pid)
+        .collect()
+}
+
+/// Parses `ps -A -ww -o pid=,args=` output, keeping adb processes.
+#[cfg_attr(any(target_os = "linux", target_os = "windows"), allow(dead_code))]
+pub fn parse_ps(output: &str) -> Vec<ProcessEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (pid, cmdline) = line.trim().split_once(char::is_whitespace)?;
+            let cmdline = cmdline.trim();
+            let argv0 = cmdline.split_whitespace().next()?;
+            if !is_adb_name(argv0) {
+                return None;
+            }
+            let exe = Path::new(argv0).is_absolute().then(|| PathBuf::from(argv0));
+            Some(ProcessEntry { pid: pid.parse().ok()?, exe, cmdline: cmdline.to_string(), env_port: None })
+        })
+        .collect()
+}
+
+/// Parses `pid|path|command line` lines from the PowerShell query in `list_adb_processes`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn parse_windows(output: &str) -> Vec<ProcessEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().splitn(3, '|');
+            let pid = parts.next()?.trim().parse().ok()?;
+            let exe = parts.next().map(str::trim).filter(|e| !e.is_empty()).map(PathBuf::from);
+            let cmdline = parts.next().unwrap_or("").trim().to_string();
+            Some(ProcessEntry { pid, exe, cmdline, env_port: None })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn list_linux() -> Vec<ProcessEntry> {
+    let Ok(entries) = std::fs::read_dir("/proc") else { return Vec::new() };
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let cmdline = std::fs::read(entry.path().join("cmdline")).ok()?;
+            let cmdline = String::from_utf8_lossy(&cmdline).replace('\0', " ").trim().to_string();
+            let argv0 = cmdline.split_whitespace().next()?;
+
+            // The exe link is unreadable for other users' processes, fall back to an absolute argv[0]
+            let exe = std::fs::read_link(entry.path().join("exe"))
+                .ok()
+                .or_else(|| Path::new(argv0).is_absolute().then(|| PathBuf::from(argv0)));
+            let name_matches = exe.as_deref().and_then(|e| e.file_name()).is_some_and(|n| is_adb_name(&n.to_string_lossy()));
+            if !name_matches && !is_adb_name(argv0) {
+                return None;
+            }
+            // Only readable for our own processes, which are the ones that matter
+            let env_port = std::fs::read(entry.path().join("environ")).ok().and_then(|e| env_port(&String::from_utf8_lossy(&e)));
+            Some(ProcessEntry { pid, exe, cmdline, env_port })
+        })
+        .collect()
+}
+
+/// Lists every adb process on the machine, whoever started it.
+pub async fn list_adb_processes() -> Vec<ProcessEntry> {
+    #[cfg(target_os = "linux")]
+    {
+        list_linux()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let script = "Get-CimInstance Win32_Process -Filter \"Name='adb.exe'\" | ForEach-Object { \"$($_.ProcessId)|$($_.ExecutablePath)|$($_.CommandLine)\" }";
+        let output = tokio::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", script])
+            .creation_flags(crate::commands::CREATE_NO_WINDOW)
+            .output()
+            .await;
+        output.map(|o| parse_windows(&String::from_utf8_lossy(&o.stdout))).unwrap_or_default()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let output = tokio::process::Command::new("ps").args(["-A", "-ww", "-o", "pid=,args="]).output().await;
+        output.map(|o| parse_ps(&String::from_utf8_lossy(&o.stdout))).unwrap_or_default()
+    }
+}
+
+/// Force kills a process, returning whether it worked.
+pub async fn kill_pid(pid: u32) -> bool {
+    #[cfg(target_os = "windows")]
+    let output = tokio::process::Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .creation_flags(crate::commands::CREATE_NO_WINDOW)
+        .output()
+        .await;
+
+    #[cfg(not(target_os = "windows"))]
+    let output = tokio::process::Command::new("kill").args(["-9", &pid.to_string()]).output().await;
+
+    output.is_ok_and(|o| o.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_port() {
+        assert_eq!(flag_port("adb -L tcp:5037 fork-server server --reply-fd 4"), Some(5037));
+        assert_eq!(flag_port("/opt/sdk/adb -L tcp:localhost:5040 fork-server server"), Some(5040));
+        assert_eq!(flag_port("adb -P 6000 fork-server server"), Some(6000));
+        assert_eq!(flag_port("adb fork-server server"), None);
+        assert_eq!(flag_port("adb -L tcp:localhost:5040 devices"), Some(5040));
+
+        assert_eq!(env_port("HOME=/root\0ANDROID_ADB_SERVER_PORT=5041\0"), Some(5041));
+        assert_eq!(env_port("ADB_SERVER_SOCKET=tcp:127.0.0.1:5042\0ANDROID_ADB_SERVER_PORT=5041\0"), Some(5042));
+        assert_eq!(env_port("XANDROID_ADB_SERVER_PORT=5041\0"), None);
+
+        let entry = |cmdline: &str, env_port| ProcessEntry { pid: 1, exe: None, cmdline: cmdline.to_string(), env_port };
+        assert_eq!(entry("adb -s emulator-5554 shell", None).target_port(), DEFAULT_ADB_PORT);
+        assert_eq!(entry("adb -s emulator-5554 shell", Some(5041)).target_port(), 5041);
+        assert_eq!(entry("adb -P 5038 shell", Some(5041)).target_port(), 5038);
+    }
+
+    #[test]
+    fn test_parse_process_lists() {
+        let ps = "  101 /opt/sdk/platform-tools/adb -L tcp:5037 fork-server server --reply-fd 4\n  202 /usr/bin/bash\n  303 adb shell\n  404 /opt/sdk/platform-tools/adb -P 5038 shell\n";
+        let entries = parse_ps(ps);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].exe.as_deref(), Some(Path::new("/opt/sdk/platform-tools/adb")));
+        assert_eq!(entries[1].exe, None);
+        assert_eq!(entries[2].target_port(), 5038);
+
+        let sdk_adb = Path::new("/opt/sdk/platform-tools/adb");
+        assert_eq!(select_own(&entries, sdk_adb, 5037), vec![101]);
+        assert_eq!(select_own(&entries, sdk_adb, 5038), vec![404]);
+
+        let win = "412|C:\\scrcpy\\adb.exe|\"C:\\scrcpy\\adb.exe\" -L tcp:5037 fork-server server --reply-fd 564\r\n77||\r\n";
+        let entries = parse_windows(win);
+        assert_eq!(entries[0].pid, 412);
+        assert_eq!(entries[0].target_port(), 5037);
+        assert_eq!(entries[1].exe, None);
+    }
+
+    #[test]
+    fn test_select_own() {
+        let ours = PathBuf::from("/nonexistent/scrcpy-bin/adb");
+        let processes = vec![
+            ProcessEntry { pid: 1, exe: Some(ours.clone()), cmdline: "adb -L tcp:5037 fork-server server".to_string(), env_port: None },
+            ProcessEntry { pid: 2, exe: Some(ours.clone()), cmdline: "adb -L tcp:5040 fork-server server".to_string(), env_port: None },
+            ProcessEntry { pid: 3, exe: Some(ours.clone()), cmdline: "adb -s R58M123ABC logcat".to_string(), env_port: None },
+            ProcessEntry { pid: 4, exe: Some(PathBuf::from("/opt/android-studio/sdk/adb")), cmdline: "adb -L tcp:5037 fork-server server".to_string(), env_port: None },
+            ProcessEntry { pid: 5, exe: None, cmdline: "adb devices".to_string(), env_port: None },
+            ProcessEntry { pid: 6, exe: Some(ours.clone()), cmdline: "adb -P 5040 shell".to_string(), env_port: None },
+            // Started by us through create_command while the server was on 5040
+            ProcessEntry { pid: 7, exe: Some(ours.clone()), cmdline: "adb -s R58M123ABC shell".to_string(), env_port: Some(5040) },
+        ];
+        assert_eq!(select_own(&processes, &ours, 5037), vec![1, 3]);
+        assert_eq!(select_own(&processes, &ours, 5040), vec![2, 6, 7]);
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::ScrcpyConfig;
+
+const PROFILE_EXTENSION: &str = "toml";
+const DEVICE_DEFAULTS_FILE: &str = "device_profiles.toml";
+
+/// Named `ScrcpyConfig` profiles stored as TOML files under the app config dir,
+/// plus a serial -> profile name mapping used as each device's default. The
+/// mapping lives next to the profiles directory so no profile name can clash with it.
+pub struct ProfileStore {
+    dir: PathBuf,
+    defaults_path: PathBuf,
+}
+
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name can't be empty".to_string());
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.')) || name.starts_with('.') {
+        return Err(format!("Invalid profile name: {}", name));
+    }
+    Ok(name)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+/// Parses a profile from JSON or TOML depending on the file extension.
+fn parse_profile(text: &str, path: &Path) -> Result<ScrcpyConfig, String> {
+    if is_json(path) {
+        serde_json::from_str(text).map_err(|e| format!("Invalid JSON profile: {}", e))
+    } else {
+        toml::from_str(text).map_err(|e| format!("Invalid TOML profile: {}", e))
+    }
+}
+
+fn serialize_profile(config: &ScrcpyConfig, path: &Path) -> Result<String, String> {
+    if is_json(path) {
+        serde_json::to_string_pretty(config).map_err(|e| e.to_string())
+    } else {
+        toml::to_string_pretty(config).map_err(|e| e.to_string())
+    }
+}
+
+impl ProfileStore {
+    pub fn new(config_dir: &Path) -> Self {
+        let dir = config_dir.join("profiles");
+        let defaults_path = config_dir.join(DEVICE_DEFAULTS_FILE);
+
+        // Older versions kept the mapping inside the profiles directory
+        let legacy = dir.join(DEVICE_DEFAULTS_FILE);
+        if legacy.exists() && !defaults_path.exists() {
+            let _ = fs::rename(&legacy, &defaults_path);
+        }
+        Self { dir, defaults_path }
+    }
+
+    fn profile_path(&self, name: &str) -> Result<PathBuf, String> {
+        Ok(self.dir.join(format!("{}.{}", validate_name(name)?, PROFILE_EXTENSION)))
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, String> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == PROFILE_EXTENSION))
+            .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.profile_path(name).map(|p| p.exists()).unwrap_or(false)
+    }
+
+    pub fn save(&self, name: &str, config: &ScrcpyConfig) -> Result<(), String> {
+        let path = self.profile_path(name)?;
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+
+        // Profiles aren't tied to a device, that's what the default mapping is for
+        let config = ScrcpyConfig { device: String::new(), ..config.clone() };
+        fs::write(&path, serialize_profile(&config, &path)?).map_err(|e| format!("Failed to save profile: {}", e))
+    }
+
+    pub fn load(&self, name: &str) -> Result<ScrcpyConfig, String> {
+        let path = self.profile_path(name)?;
+        let text = fs::read_to_string(&path).map_err(|_| format!("Profile not found: {}", name))?;
+        parse_profile(&text, &path)
+    }
+
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        let from_path = self.profile_path(from)?;
+        let to_path = self.profile_path(to)?;
+        if !from_path.exists() {
+            return Err(format!("Profile not found: {}", from));
+        }
+        if to_path.exists() {
+            return Err(format!("Profile already exists: {}", to));
+        }
+        fs::rename(&from_path, &to_path).map_err(|e| e.to_string())?;
+
+        let mut defaults = self.device_defaults()?;
+        let mut changed = false;
+        for profile in defaults.values_mut().filter(|p| p.as_str() == from.trim()) {
+            *profile = to.trim().to_string();
+            changed = true;
+        }
+        if changed { self.write_device_defaults(&defaults)?; }
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let path = self.profile_path(name)?;
+        fs::remove_file(&path).map_err(|_| format!("Profile not found: {}", name))?;
+
+        let mut defaults = self.device_defaults()?;
+        let before = defaults.len();
+        defaults.retain(|_, profile| profile.as_str() != name.trim());
+        if defaults.len() != before { self.write_device_defaults(&defaults)?; }
+        Ok(())
+    }
+
+    /// Imports a `.toml` or `.json` profile file, named after the file unless a name is given.
+    pub fn import(&self, source: &Path, name: Option<&str>) -> Result<String, String> {
+        let text = fs::read_to_string(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let config = parse_profile(&text, source)?;
+
+        let name = match name {
+            Some(n) => n.to_string(),
+            None => source.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        };
+        self.save(&name, &config)?;
+        Ok(validate_name(&name)?.to_string())
+    }
+
+    /// Exports a profile to `destination`, as JSON if it ends in `.json` and TOML otherwise.
+    pub fn export(&self, name: &str, destination: &Path) -> Result<(), String> {
+        let config = self.load(name)?;
+        fs::write(destination, serialize_profile(&config, destination)?).map_err(|e| format!("Failed to export profile: {}", e))
+    }
+
+    pub fn device_defaults(&self) -> Result<BTreeMap<String, String>, String> {
+        match fs::read_to_string(&self.defaults_path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("Invalid device profile mapping: {}", e)),
+            Err(_) => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn write_device_defaults(&self, defaults: &BTreeMap<String, String>) -> Result<(), String> {
+        if let Some(parent) = self.defaults_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let text = toml::to_string(defaults).map_err(|e| e.to_string())?;
+        fs::write(&self.defaults_path, text).map_err(|e| e.to_string())
+    }
+
+    /// Sets or clears (`None`) the default profile for a device serial.
+    pub fn set_device_default(&self, serial: &str, profile: Option<&str>) -> Result<(), String> {
+        let mut defaults = self.device_defaults()?;
+        match profile {
+            Some(name) => {
+                if !self.exists(name) {
+                    return Err(format!("Profile not found: {}", name));
+                }
+                defaults.insert(serial.to_string(), name.trim().to_string());
+            }
+            None => {
+                defaults.remove(serial);
+            }
+        }
+        self.write_device_defaults(&defaults)
+    }
+
+    /// Loads `profile`, or the device's default profile when none is given, targeted at `serial`.
+    pub fn resolve(&self, serial: &str, profile: Option<&str>) -> Result<ScrcpyConfig, String> {
+        let name = match profile {
+            Some(name) => name.to_string(),
+            None => self
+                .device_defaults()?
+                .remove(serial)
+                .ok_or_else(|| format!("No default profile set for {}", serial))?,
+        };
+
+        let config = self.load(&name)?;
+        Ok(ScrcpyConfig { device: serial.to_string(), ..config })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SessionMode, VideoCodec};
+
+    fn temp_store(tag: &str) -> (ProfileStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("scrcpy_gui_profiles_{}_{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        (ProfileStore::new(&dir), dir)
+    }
+
+    #[test]
+    fn test_profile_lifecycle() {
+        let (store, dir) = temp_store("lifecycle");
+        let config = ScrcpyConfig {
+            device: "R58M123ABC".to_string(),
+            session_mode: SessionMode::Camera,
+            codec: Some(VideoCodec::H265),
+            fps: Some(30),
+            ..Default::default()
+        };
+
+        store.save("Demo", &config).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["Demo".to_string()]);
+
+        let loaded = store.load("Demo").unwrap();
+        assert_eq!(loaded.device, "");
+        assert_eq!(loaded.session_mode, SessionMode::Camera);
+        assert_eq!(loaded.codec, Some(VideoCodec::H265));
+
+        store.set_device_default("R58M123ABC", Some("Demo")).unwrap();
+        store.rename("Demo", "Camera 30").unwrap();
+        assert_eq!(store.device_defaults().unwrap().get("R58M123ABC").map(String::as_str), Some("Camera 30"));
+
+        let resolved = store.resolve("R58M123ABC", None).unwrap();
+        assert_eq!(resolved.device, "R58M123ABC");
+        assert_eq!(resolved.fps, Some(30));
+
+        store.delete("Camera 30").unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.device_defaults().unwrap().is_empty());
+        assert!(store.resolve("R58M123ABC", None).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_profile_named_like_device_mapping() {
+        let (store, dir) = temp_store("mapping");
+        store.save("Demo", &ScrcpyConfig::default()).unwrap();
+        store.set_device_default("R58M123ABC", Some("Demo")).unwrap();
+
+        store.save("device_profiles", &ScrcpyConfig { fps: Some(60), ..Default::default() }).unwrap();
+        assert_eq!(store.list().unwrap(), vec!["Demo".to_string(), "device_profiles".to_string()]);
+        assert_eq!(store.load("device_profiles").unwrap().fps, Some(60));
+        assert_eq!(store.device_defaults().unwrap().get("R58M123ABC").map(String::as_str), Some("Demo"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_legacy_mapping_is_moved() {
+        let dir = std::env::temp_dir().join(format!("scrcpy_gui_profiles_legacy_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("profiles")).unwrap();
+        fs::write(dir.join("profiles").join(DEVICE_DEFAULTS_FILE), "R58M123ABC = \"Demo\"\n").unwrap();
+
+        let store = ProfileStore::new(&dir);
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.device_defaults().unwrap().get("R58M123ABC").map(String::as_str), Some("Demo"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_profile_import_export() {
+        let (store, dir) = temp_store("import");
+        store.save("Base", &ScrcpyConfig { bitrate: Some(16), ..Default::default() }).unwrap();
+
+        let exported = dir.join("shared.json");
+        store.export("Base", &exported).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&exported).unwrap()).unwrap();
+        assert_eq!(json["bitrate"], 16);
+
+        let name = store.import(&exported, None).unwrap();
+        assert_eq!(name, "shared");
+        assert_eq!(store.load("shared").unwrap().bitrate, Some(16));
+
+        assert!(store.save("../escape", &ScrcpyConfig::default()).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use serde::Serialize;
+
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+const MKV_SEGMENT_ID: [u8; 4] = [0x18, 0x53, 0x80, 0x67];
+
+/// State of a recording file after its scrcpy process ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingCheck {
+    pub path: String,
+    pub exists: bool,
+    pub size: u64,
+    /// Whether the muxer wrote its trailer. `None` for containers we can't inspect.
+    pub finalized: Option<bool>,
+}
+
+/// The output file of a `--record=` argument, if any.
+pub fn recording_path(args: &[String]) -> Option<&str> {
+    args.iter().find_map(|a| a.strip_prefix("--record="))
+}
+
+/// Reads an EBML variable length integer, returning (value, length). `None` as
+/// value means the "unknown size" marker (all value bits set).
+fn read_vint(bytes: &[u8]) -> Option<(Option<u64>, usize)> {
+    let first = *bytes.first()?;
+    if first == 0 {
+        return None;
+    }
+    let len = first.leading_zeros() as usize + 1;
+    let bytes = bytes.get(..len)?;
+
+    let mut value = first as u64 & ((1u64 << (8 - len)) - 1);
+    for b in &bytes[1..] {
+        value = (value << 8) | *b as u64;
+    }
+    let unknown = value == (1u64 << (7 * len)) - 1;
+    Some((if unknown { None } else { Some(value) }, len))
+}
+
+/// ffmpeg writes Matroska segments with an unknown size and patches it in the
+/// trailer, so a known size means the file was closed properly.
+pub fn is_mkv_finalized(header: &[u8]) -> Option<bool> {
+    if !header.starts_with(&EBML_MAGIC) {
+        return Some(false);
+    }
+    let (ebml_size, len) = read_vint(&header[4..])?;
+    let segment = 4 + len + ebml_size? as usize;
+
+    if header.get(segment..segment + 4)? != MKV_SEGMENT_ID {
+        return Some(false);
+    }
+    let (segment_size, _) = read_vint(&header[segment + 4..])?;
+    Some(segment_size.is_some())
+}
+
+/// MP4 files only get their `moov` box when the muxer finishes.
+pub fn is_mp4_finalized<R: Read + Seek>(reader: &mut R) -> std::io::Result<bool> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[4..] == b"moov" {
+            return Ok(true);
+        }
+
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64 {
+            0 => return Ok(false), // Box runs to the end of the file
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large)?;
+                u64::from_be_bytes(large)
+            }
+            size => size,
+        };
+        if size < 8 {
+            return Ok(false);
+        }
+        pos += size;
+    }
+    Ok(false)
+}
+
+pub fn check_recording(path: &Path) -> RecordingCheck {
+    let mut check = RecordingCheck {
+        path: path.to_string_lossy().to_string(),
+        exists: false,
+        size: 0,
+        finalized: Some(false),
+    };
+
+    let Ok(mut file) = File::open(path) else { return check };
+    check.exists = true;
+    check.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if check.size == 0 {
+        return check;
+    }
+
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    check.finalized = match extension.as_str() {
+        "mkv" | "mka" => {
+            let mut header = vec![0u8; 4096];
+            let read = file.read(&mut header).unwrap_or(0);
+            header.truncate(read);
+            Some(is_mkv_finalized(&header).unwrap_or(false))
+        }
+        "mp4" | "m4a" => Some(is_mp4_finalized(&mut file).unwrap_or(false)),
+        _ => None,
+    };
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mkv_header(segment_size: &[u8]) -> Vec<u8> {
+        let mut bytes = EBML_MAGIC.to_vec();
+        bytes.push(0x83); // 3 byte EBML header body
+        bytes.extend_from_slice(&[0x42, 0x86, 0x81]);
+        bytes.extend_from_slice(&MKV_SEGMENT_ID);
+        bytes.extend_from_slice(segment_size);
+        bytes
+    }
+
+    #[test]
+    fn test_mkv_segment_size() {
+        assert_eq!(is_mkv_finalized(&mkv_header(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF])), Some(false));
+        assert_eq!(is_mkv_finalized(&mkv_header(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56])), Some(true));
+        assert_eq!(is_mkv_finalized(b"not a matroska file"), Some(false));
+    }
+
+    #[test]
+    fn test_mp4_moov() {
+        let mut truncated = Vec::new();
+        truncated.extend_from_slice(&[0, 0, 0, 16]);
+        truncated.extend_from_slice(b"ftypisom\0\0\0\0");
+        truncated.extend_from_slice(&[0, 0, 0, 12]);
+        truncated.extend_from_slice(b"mdat\0\0\0\0");
+        assert!(!is_mp4_finalized(&mut Cursor::new(truncated.clone())).unwrap());
+
+        let mut complete = truncated;
+        complete.extend_from_slice(&[0, 0, 0, 8]);
+        complete.extend_from_slice(b"moov");
+        assert!(is_mp4_finalized(&mut Cursor::new(complete)).unwrap());
+    }
+
+    #[test]
+    fn test_check_recording_missing_file() {
+        let args = vec!["--no-window".to_string(), "--record=/nonexistent/scrcpy_test.mkv".to_string()];
+        let path = recording_path(&args).unwrap();
+        let check = check_recording(Path::new(path));
+        assert!(!check.exists);
+        assert_eq!(check.finalized, Some(false));
+    }
+}
@@ -0,0 +1,192 @@
+use serde::Serialize;
+use crate::adb::{remote_join, RemoteDirEntry, S_IFDIR, S_IFLNK, S_IFMT, S_IFREG};
+
+/// An entry of a device directory, as shown by the file browser.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEntry {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// Full `st_mode`, file type bits included.
+    pub mode: u32,
+    /// Seconds since the epoch. From `ls -la` this is the device's local time read as UTC.
+    pub mtime: i64,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+}
+
+impl RemoteEntry {
+    pub fn from_sync(dir: &str, entry: RemoteDirEntry) -> Self {
+        Self {
+            path: remote_join(dir, &entry.name),
+            name: entry.name,
+            size: entry.stat.size,
+            mode: entry.stat.mode,
+            mtime: entry.stat.mtime as i64,
+            is_dir: entry.stat.is_dir(),
+            is_symlink: entry.stat.is_symlink(),
+            symlink_target: None,
+        }
+    }
+}
+
+/// Quotes a path for the device shell.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Converts `ls` permissions like `drwxrwx--x` or `-rwsr-xr-t` to a mode.
+pub fn parse_mode(perms: &str) -> Option<u32> {
+    let chars: Vec<char> = perms.chars().collect();
+    if chars.len() < 10 {
+        return None;
+    }
+
+    let file_type = match chars[0] {
+        'd' => S_IFDIR,
+        'l' => S_IFLNK,
+        '-' => S_IFREG,
+        'c' => 0o020000,
+        'b' => 0o060000,
+        'p' => 0o010000,
+        's' => 0o140000,
+        _ => return None,
+    };
+
+    let mut mode = file_type;
+    for (i, c) in chars[1..10].iter().enumerate() {
+        let bit = 1 << (8 - i);
+        match (i % 3, c) {
+            (_, '-') => {}
+            (0, 'r') | (1, 'w') | (2, 'x') => mode |= bit,
+            // setuid/setgid/sticky, lowercase when the x bit is also set
+            (2, 's' | 't') => mode |= bit | special_bit(i),
+            (2, 'S' | 'T') => mode |= special_bit(i),
+            _ => return None,
+        }
+    }
+    Some(mode)
+}
+
+fn special_bit(index: usize) -> u32 {
+    match index {
+        2 => 0o4000,
+        5 => 0o2000,
+        _ => 0o1000,
+    }
+}
+
+/// Splits off the next whitespace separated field, returning it and the rest.
+fn next_field(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace)?;
+    Some((&s[..end], &s[end..]))
+}
+
+/// Parses one `ls -la` line from toybox, e.g.
+/// `lrwxrwxrwx  1 root root  21 2009-01-01 02:00 sdcard -> /storage/self/primary`.
+pub fn parse_ls_line(dir: &str, line: &str) -> Option<RemoteEntry> {
+    let (perms, rest) = next_field(line)?;
+    let mode = parse_mode(perms)?;
+    let (_links, rest) = next_field(rest)?;
+    let (_owner, rest) = next_field(rest)?;
+    let (_group, rest) = next_field(rest)?;
+
+    // Device nodes show "major, minor" instead of a size
+    let (size, rest) = next_field(rest)?;
+    let (size, rest) = if size.ends_with(',') { (0, next_field(rest)?.1) } else { (size.parse().ok()?, rest) };
+
+    let (date, rest) = next_field(rest)?;
+    let (time, rest) = next_field(rest)?;
+    let mtime = chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M")
+        .map(|t| t.and_utc().timestamp())
+        .unwrap_or(0);
+
+    let name = rest.trim_start();
+    let is_symlink = mode & S_IFMT == S_IFLNK;
+    let (name, symlink_target) = match name.split_once(" -> ") {
+        Some((name, target)) if is_symlink => (name, Some(target.to_string())),
+        _ => (name, None),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    Some(RemoteEntry {
+        name: name.to_string(),
+        path: remote_join(dir, name),
+        size,
+        mode,
+        mtime,
+        is_dir: mode & S_IFMT == S_IFDIR,
+        is_symlink,
+        symlink_target,
+    })
+}
+
+pub fn parse_ls(dir: &str, output: &str) -> Vec<RemoteEntry> {
+    output
+        .lines()
+        .filter_map(|line| parse_ls_line(dir, line.trim_end_matches('\r')))
+        .collect()
+}
+
+/// Directories first, then by name.
+pub fn sort_entries(entries: &mut [RemoteEntry]) {
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LS: &str = "total 48\n\
+drwxrwx--x  4 root    sdcard_rw 3452 2024-01-15 10:30 .\n\
+drwx--x--x  4 root    sdcard_rw 3452 2024-01-15 10:30 ..\n\
+-rw-rw----  1 u0_a123 media_rw  1234 2024-01-15 10:30 holiday photo.jpg\n\
+lrwxrwxrwx  1 root    root        21 2009-01-01 02:00 sdcard -> /storage/self/primary\n\
+drwxrwx--x  2 root    sdcard_rw 3452 2024-02-01 08:05 Music\n\
+crw-rw-rw-  1 root    root     1,   3 2024-01-15 10:30 null\r\n";
+
+    #[test]
+    fn test_parse_ls() {
+        let entries = parse_ls("/sdcard/", LS);
+        assert_eq!(entries.len(), 4);
+
+        let photo = &entries[0];
+        assert_eq!(photo.name, "holiday photo.jpg");
+        assert_eq!(photo.path, "/sdcard/holiday photo.jpg");
+        assert_eq!(photo.size, 1234);
+        assert_eq!(photo.mode, S_IFREG | 0o660);
+        assert_eq!(photo.mtime, 1705314600);
+        assert!(!photo.is_dir);
+
+        let link = &entries[1];
+        assert!(link.is_symlink);
+        assert_eq!(link.name, "sdcard");
+        assert_eq!(link.symlink_target.as_deref(), Some("/storage/self/primary"));
+
+        assert!(entries[2].is_dir);
+        assert_eq!(entries[3].name, "null");
+        assert_eq!(entries[3].size, 0);
+    }
+
+    #[test]
+    fn test_parse_mode_special_bits() {
+        assert_eq!(parse_mode("-rwsr-xr-x"), Some(S_IFREG | 0o4755));
+        assert_eq!(parse_mode("drwxrwxrwt"), Some(S_IFDIR | 0o1777));
+        assert_eq!(parse_mode("-rw-r-S---"), Some(S_IFREG | 0o2640));
+        assert_eq!(parse_mode("total"), None);
+    }
+
+    #[test]
+    fn test_sort_and_quote() {
+        let mut entries = parse_ls("/sdcard", LS);
+        sort_entries(&mut entries);
+        assert_eq!(entries[0].name, "Music");
+
+        assert_eq!(shell_quote("it's here"), "'it'\\''s here'");
+    }
+}
@@ -0,0 +1,175 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Verbose,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Known scrcpy log lines worth reacting to in the UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LogEventKind {
+    #[serde(rename_all = "camelCase")]
+    Device { name: String, android_version: Option<String> },
+    Renderer { name: String },
+    Texture { width: u32, height: u32 },
+    RecordingStarted { path: String },
+    RecordingFinished { path: String },
+    EncoderFailure { message: String },
+    DeviceDisconnected,
+}
+
+/// A classified scrcpy output line, emitted as the `scrcpy-event` payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrcpyLogEvent {
+    pub session_id: String,
+    /// `None` for lines without a level prefix, like the version banner or stack traces.
+    pub level: Option<LogLevel>,
+    /// Whether the line came from the device-side server (`[server]` prefix).
+    pub from_server: bool,
+    pub timestamp: String,
+    pub kind: Option<LogEventKind>,
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedLine<'a> {
+    pub level: Option<LogLevel>,
+    pub from_server: bool,
+    pub message: &'a str,
+    pub kind: Option<LogEventKind>,
+}
+
+fn split_level(line: &str) -> (Option<LogLevel>, &str) {
+    const LEVELS: [(&str, LogLevel); 5] = [
+        ("VERBOSE:", LogLevel::Verbose),
+        ("DEBUG:", LogLevel::Debug),
+        ("INFO:", LogLevel::Info),
+        ("WARN:", LogLevel::Warn),
+        ("ERROR:", LogLevel::Error),
+    ];
+
+    for (prefix, level) in LEVELS {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            return (Some(level), rest.trim());
+        }
+    }
+    (None, line)
+}
+
+/// `Device: [samsung] samsung SM-A525F (Android 13)`
+fn parse_device(rest: &str) -> LogEventKind {
+    let (name, android_version) = match rest.rfind("(Android ") {
+        Some(start) => (
+            rest[..start].trim(),
+            Some(rest[start + 9..].trim_end_matches(')').trim().to_string()),
+        ),
+        None => (rest.trim(), None),
+    };
+    LogEventKind::Device { name: name.to_string(), android_version }
+}
+
+fn detect_kind(level: Option<LogLevel>, message: &str) -> Option<LogEventKind> {
+    if let Some(rest) = message.strip_prefix("Device: ") {
+        return Some(parse_device(rest));
+    }
+    if let Some(rest) = message.strip_prefix("Renderer: ") {
+        return Some(LogEventKind::Renderer { name: rest.trim().to_string() });
+    }
+    if let Some(rest) = message.strip_prefix("Texture: ") {
+        let (w, h) = rest.trim().split_once('x')?;
+        return Some(LogEventKind::Texture { width: w.parse().ok()?, height: h.parse().ok()? });
+    }
+    if message.starts_with("Recording started to ") {
+        let path = message.split_once(": ").map(|(_, p)| p.trim()).unwrap_or("");
+        return Some(LogEventKind::RecordingStarted { path: path.to_string() });
+    }
+    if message.starts_with("Recording complete to ") {
+        let path = message.split_once(": ").map(|(_, p)| p.trim()).unwrap_or("");
+        return Some(LogEventKind::RecordingFinished { path: path.to_string() });
+    }
+    if message.contains("Device disconnected") {
+        return Some(LogEventKind::DeviceDisconnected);
+    }
+    if level == Some(LogLevel::Error) && message.to_lowercase().contains("encod") {
+        return Some(LogEventKind::EncoderFailure { message: message.to_string() });
+    }
+    None
+}
+
+/// Classifies one line of scrcpy output, e.g. `[server] INFO: Device: [Google] Pixel 7 (Android 14)`.
+pub fn parse_line(line: &str) -> ParsedLine<'_> {
+    let line = line.trim_end_matches('\r').trim_start();
+    let (from_server, rest) = match line.strip_prefix("[server]") {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, line),
+    };
+
+    let (level, message) = split_level(rest);
+    ParsedLine { level, from_server, message, kind: detect_kind(level, message) }
+}
+
+pub fn to_event(session_id: &str, line: &str) -> ScrcpyLogEvent {
+    let parsed = parse_line(line);
+    ScrcpyLogEvent {
+        session_id: session_id.to_string(),
+        level: parsed.level,
+        from_server: parsed.from_server,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        kind: parsed.kind,
+        raw: line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_events() {
+        let line = parse_line("[server] INFO: Device: [samsung] samsung SM-A525F (Android 13)");
+        assert!(line.from_server);
+        assert_eq!(line.level, Some(LogLevel::Info));
+        assert_eq!(line.kind, Some(LogEventKind::Device {
+            name: "[samsung] samsung SM-A525F".to_string(),
+            android_version: Some("13".to_string()),
+        }));
+
+        assert_eq!(parse_line("INFO: Renderer: opengl").kind, Some(LogEventKind::Renderer { name: "opengl".to_string() }));
+        assert_eq!(parse_line("INFO: Texture: 1080x2400").kind, Some(LogEventKind::Texture { width: 1080, height: 2400 }));
+        assert_eq!(
+            parse_line("INFO: Recording started to mkv file: /home/me/Videos/a.mkv").kind,
+            Some(LogEventKind::RecordingStarted { path: "/home/me/Videos/a.mkv".to_string() })
+        );
+        assert_eq!(
+            parse_line("INFO: Recording complete to mkv file: C:\\Videos\\a.mkv\r").kind,
+            Some(LogEventKind::RecordingFinished { path: "C:\\Videos\\a.mkv".to_string() })
+        );
+        assert_eq!(parse_line("WARN: Device disconnected").kind, Some(LogEventKind::DeviceDisconnected));
+
+        let encoder = parse_line("[server] ERROR: Encoding error: android.media.MediaCodec$CodecException: Error 0xfffffff4");
+        assert_eq!(encoder.level, Some(LogLevel::Error));
+        assert!(matches!(encoder.kind, Some(LogEventKind::EncoderFailure { .. })));
+    }
+
+    #[test]
+    fn test_parse_plain_lines() {
+        let banner = parse_line("scrcpy 3.1 <https://github.com/Genymobile/scrcpy>");
+        assert_eq!(banner.level, None);
+        assert_eq!(banner.kind, None);
+
+        let debug = parse_line("DEBUG: Using SDL_RENDER_DRIVER=opengl");
+        assert_eq!(debug.level, Some(LogLevel::Debug));
+        assert!(!debug.from_server);
+        assert_eq!(debug.message, "Using SDL_RENDER_DRIVER=opengl");
+
+        // Non-error lines mentioning encoders aren't failures
+        assert_eq!(parse_line("INFO: Using encoder: 'c2.qti.avc.encoder'").kind, None);
+    }
+}
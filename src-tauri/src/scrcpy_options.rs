@@ -0,0 +1,301 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderKind {
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderType {
+    Hardware,
+    Software,
+    Hybrid,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Encoder {
+    pub kind: EncoderKind,
+    pub codec: String,
+    pub name: String,
+    pub encoder_type: EncoderType,
+    pub vendor: bool,
+    pub alias_for: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Display {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraSize {
+    pub width: u32,
+    pub height: u32,
+    /// Only set for high speed sizes, which have their own fps list.
+    pub fps: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Camera {
+    pub id: String,
+    pub facing: String,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub fps: Vec<u32>,
+    pub sizes: Vec<CameraSize>,
+    pub high_speed_sizes: Vec<CameraSize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct App {
+    pub package: String,
+    pub label: String,
+    pub system: bool,
+}
+
+/// Splits scrcpy output into lines, dropping the `\r` left over on Windows.
+fn clean_lines(output: &str) -> impl Iterator<Item = &str> {
+    output.lines().map(|l| l.trim_end_matches('\r'))
+}
+
+fn flag_value<'a>(line: &'a str, flag: &str) -> Option<&'a str> {
+    let start = line.find(flag)? + flag.len();
+    line[start..].split_whitespace().next()
+}
+
+fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (w, h) = s.trim().split_once('x')?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+/// Parses `fps=[15, 24, 30]` anywhere in the string.
+fn parse_fps_list(s: &str) -> Vec<u32> {
+    s.find("fps=[")
+        .and_then(|start| {
+            let rest = &s[start + 5..];
+            rest.find(']').map(|end| &rest[..end])
+        })
+        .map(|list| list.split(',').filter_map(|v| v.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Parses `scrcpy --list-encoders`, e.g.
+/// `--video-codec=h264 --video-encoder=c2.qti.avc.encoder (hw) [vendor]`.
+pub fn parse_encoders(output: &str) -> Vec<Encoder> {
+    clean_lines(output)
+        .filter_map(|line| {
+            let (kind, codec_flag, encoder_flag) = if line.contains("--video-encoder=") {
+                (EncoderKind::Video, "--video-codec=", "--video-encoder=")
+            } else if line.contains("--audio-encoder=") {
+                (EncoderKind::Audio, "--audio-codec=", "--audio-encoder=")
+            } else {
+                return None;
+            };
+
+            let encoder_type = if line.contains("(hw)") {
+                EncoderType::Hardware
+            } else if line.contains("(sw)") {
+                EncoderType::Software
+            } else if line.contains("(hybrid)") {
+                EncoderType::Hybrid
+            } else {
+                EncoderType::Unknown
+            };
+
+            let alias_for = line
+                .find("(alias for ")
+                .and_then(|start| {
+                    let rest = &line[start + 11..];
+                    rest.find(')').map(|end| rest[..end].trim().to_string())
+                });
+
+            Some(Encoder {
+                kind,
+                codec: flag_value(line, codec_flag).unwrap_or_default().to_string(),
+                name: flag_value(line, encoder_flag)?.to_string(),
+                encoder_type,
+                vendor: line.contains("[vendor]"),
+                alias_for,
+            })
+        })
+        .collect()
+}
+
+/// Parses `scrcpy --list-displays`, e.g. `--display-id=0    (1080x2400)`.
+pub fn parse_displays(output: &str) -> Vec<Display> {
+    clean_lines(output)
+        .filter_map(|line| {
+            let id = flag_value(line, "--display-id=")?.parse().ok()?;
+            let start = line.find('(')? + 1;
+            let end = line[start..].find(')')? + start;
+            let (width, height) = parse_size(&line[start..end])?;
+            Some(Display { id, width, height })
+        })
+        .collect()
+}
+
+/// Parses `scrcpy --list-cameras` and `--list-camera-sizes`. The latter lists sizes
+/// below each camera line, with high speed sizes in a separate sub-section.
+pub fn parse_cameras(output: &str) -> Vec<Camera> {
+    let mut cameras: Vec<Camera> = Vec::new();
+    let mut in_high_speed = false;
+
+    for line in clean_lines(output) {
+        let trimmed = line.trim();
+
+        if let Some(id) = flag_value(line, "--camera-id=") {
+            // e.g. "--camera-id=0    (back, 4000x3000, fps=[15, 24, 30])"
+            let details = line
+                .find('(')
+                .map(|start| line[start + 1..].trim_end().trim_end_matches(')'))
+                .unwrap_or("");
+            let mut parts = details.splitn(3, ", ");
+            let facing = parts.next().unwrap_or("").trim().to_string();
+            let max_size = parts.next().and_then(parse_size);
+
+            cameras.push(Camera {
+                id: id.to_string(),
+                facing,
+                max_width: max_size.map(|s| s.0),
+                max_height: max_size.map(|s| s.1),
+                fps: parse_fps_list(details),
+                sizes: Vec::new(),
+                high_speed_sizes: Vec::new(),
+            });
+            in_high_speed = false;
+        } else if trimmed.starts_with("High speed capture") {
+            in_high_speed = true;
+        } else if let Some(entry) = trimmed.strip_prefix("- ") {
+            let Some(camera) = cameras.last_mut() else { continue };
+            let size_str = entry.split_whitespace().next().unwrap_or("");
+            if let Some((width, height)) = parse_size(size_str) {
+                let size = CameraSize { width, height, fps: parse_fps_list(entry) };
+                if in_high_speed {
+                    camera.high_speed_sizes.push(size);
+                } else {
+                    camera.sizes.push(size);
+                }
+            }
+        }
+    }
+
+    cameras
+}
+
+/// Parses `scrcpy --list-apps`, where `*` marks system apps and `-` user apps:
+/// ` * Chrome                     com.android.chrome`.
+pub fn parse_apps(output: &str) -> Vec<App> {
+    clean_lines(output)
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            let (system, rest) = if let Some(rest) = trimmed.strip_prefix("* ") {
+                (true, rest)
+            } else if let Some(rest) = trimmed.strip_prefix("- ") {
+                (false, rest)
+            } else {
+                return None;
+            };
+
+            let (label, package) = rest.trim().rsplit_once(char::is_whitespace)?;
+            Some(App {
+                package: package.to_string(),
+                label: label.trim().to_string(),
+                system,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODERS: &str = "scrcpy 3.1 <https://github.com/Genymobile/scrcpy>\n\
+INFO: ADB device found:\n\
+INFO:     -->   (usb)  R58M123ABC                      device  SM_A525F\n\
+[server] INFO: List of video encoders:\n\
+    --video-codec=h264 --video-encoder=c2.qti.avc.encoder         (hw) [vendor]\n\
+    --video-codec=h264 --video-encoder=c2.android.avc.encoder     (sw)\n\
+    --video-codec=h265 --video-encoder=OMX.qcom.video.encoder.hevc (hw) [vendor] (alias for c2.qti.hevc.encoder)\n\
+[server] INFO: List of audio encoders:\n\
+    --audio-codec=opus --audio-encoder=c2.android.opus.encoder    (sw)\n\
+    --audio-codec=aac --audio-encoder=c2.android.aac.encoder      (sw)\r\n";
+
+    const CAMERA_SIZES: &str = "[server] INFO: List of cameras:\n\
+    --camera-id=0    (back, 4000x3000, fps=[15, 24, 30])\n\
+        - 4000x3000\n\
+        - 1920x1080\n\
+      High speed capture (--camera-high-speed):\n\
+        - 1920x1080 (fps=[120, 240])\n\
+        - 1280x720 (fps=[120, 240, 480])\n\
+    --camera-id=1    (front, 3264x2448, fps=[15, 30])\n\
+        - 3264x2448\n";
+
+    #[test]
+    fn test_parse_encoders() {
+        let encoders = parse_encoders(ENCODERS);
+        assert_eq!(encoders.len(), 5);
+
+        assert_eq!(encoders[0].kind, EncoderKind::Video);
+        assert_eq!(encoders[0].codec, "h264");
+        assert_eq!(encoders[0].name, "c2.qti.avc.encoder");
+        assert_eq!(encoders[0].encoder_type, EncoderType::Hardware);
+        assert!(encoders[0].vendor);
+
+        assert_eq!(encoders[1].encoder_type, EncoderType::Software);
+        assert!(!encoders[1].vendor);
+        assert_eq!(encoders[2].alias_for.as_deref(), Some("c2.qti.hevc.encoder"));
+
+        assert_eq!(encoders[4].kind, EncoderKind::Audio);
+        assert_eq!(encoders[4].codec, "aac");
+        assert_eq!(encoders[4].name, "c2.android.aac.encoder");
+    }
+
+    #[test]
+    fn test_parse_displays() {
+        let displays = parse_displays("[server] INFO: List of displays:\n    --display-id=0    (1080x2400)\n    --display-id=2    (1920x1080)\n");
+        assert_eq!(displays, vec![
+            Display { id: 0, width: 1080, height: 2400 },
+            Display { id: 2, width: 1920, height: 1080 },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_cameras() {
+        let cameras = parse_cameras(CAMERA_SIZES);
+        assert_eq!(cameras.len(), 2);
+
+        let back = &cameras[0];
+        assert_eq!(back.id, "0");
+        assert_eq!(back.facing, "back");
+        assert_eq!((back.max_width, back.max_height), (Some(4000), Some(3000)));
+        assert_eq!(back.fps, vec![15, 24, 30]);
+        assert_eq!(back.sizes.len(), 2);
+        assert_eq!(back.high_speed_sizes.len(), 2);
+        assert_eq!(back.high_speed_sizes[1], CameraSize { width: 1280, height: 720, fps: vec![120, 240, 480] });
+
+        assert_eq!(cameras[1].facing, "front");
+        assert_eq!(cameras[1].sizes.len(), 1);
+        assert!(cameras[1].high_speed_sizes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_apps() {
+        let apps = parse_apps("[server] INFO: List of apps:\n * Chrome                     com.android.chrome\n - My Test App               com.example.test\n");
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0], App { package: "com.android.chrome".to_string(), label: "Chrome".to_string(), system: true });
+        assert_eq!(apps[1].label, "My Test App");
+        assert!(!apps[1].system);
+    }
+}
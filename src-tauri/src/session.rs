@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use tokio::process::Child;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+use crate::config::SessionMode;
+use crate::recording::RecordingCheck;
+
+/// How many trailing stderr lines are kept to explain an exit.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// How long a stopped session gets to finalize its recording before it's killed.
+pub const DEFAULT_STOP_GRACE: Duration = Duration::from_secs(5);
+
+/// Public description of a running scrcpy session, as returned by `list_sessions`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: String,
+    pub device: String,
+    pub mode: SessionMode,
+    pub started_at: String,
+    pub pid: Option<u32>,
+    pub args: Vec<String>,
+}
+
+/// How a scrcpy process ended.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitInfo {
+    pub success: bool,
+    pub code: Option<i32>,
+    /// Terminating signal on Unix, always `None` on Windows.
+    pub signal: Option<i32>,
+    pub stderr_tail: Vec<String>,
+    pub error: Option<String>,
+    /// The process was stopped through `stop_scrcpy` rather than exiting on its own.
+    pub requested: bool,
+    /// It ignored the graceful stop for the whole grace period and had to be killed.
+    pub forced: bool,
+}
+
+impl ExitInfo {
+    fn from_status(status: ExitStatus, stderr_tail: Vec<String>) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self { success: status.success(), code: status.code(), signal, stderr_tail, error: None, requested: false, forced: false }
+    }
+
+    fn from_error(error: std::io::Error, stderr_tail: Vec<String>) -> Self {
+        Self { success: false, code: None, signal: None, stderr_tail, error: Some(error.to_string()), requested: false, forced: false }
+    }
+}
+
+/// Requests sent to a session's supervisor task, which owns the child process.
+pub enum SessionControl {
+    /// Terminates gracefully, killing the process if it's still running after `grace`.
+    Stop { grace: Duration, reply: oneshot::Sender<ExitInfo> },
+}
+
+/// Result of `stop_scrcpy`: how the process ended and, when it was recording,
+/// whether the file was finalized.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopReport {
+    pub session_id: String,
+    pub exit: ExitInfo,
+    pub recording: Option<RecordingCheck>,
+}
+
+/// A session `stop_all_sessions` couldn't stop cleanly.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopFailure {
+    pub session_id: String,
+    pub error: String,
+}
+
+/// Result of `stop_all_sessions`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopAllReport {
+    pub stopped: Vec<StopReport>,
+    pub failed: Vec<StopFailure>,
+}
+
+/// A running scrcpy session. The `Child` itself lives in the supervisor task,
+/// everything else talks to it through `control`.
+pub struct ScrcpySession {
+    pub info: SessionInfo,
+    pub control: mpsc::UnboundedSender<SessionControl>,
+}
+
+/// Rolling buffer of the last stderr lines of a process.
+#[derive(Clone, Default)]
+pub struct StderrTail(Arc<Mutex<VecDeque<String>>>);
+
+impl StderrTail {
+    pub fn push(&self, line: &str) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == STDERR_TAIL_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    pub fn snapshot(&self) -> Vec<String> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Asks the process to exit on its own so scrcpy can finalize recordings.
+pub fn terminate_gracefully(pid: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        let _ = std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string()])
+            .creation_flags(crate::commands::CREATE_NO_WINDOW)
+            .output();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // SIGTERM
+        let _ = std::process::Command::new("kill")
+            .arg(pid.to_string())
+            .output();
+    }
+}
+
+/// Owns `child` until it exits, handling stop requests in the meantime, and
+/// reports how it ended. `output_done` is the stderr reader, awaited briefly so
+/// the last lines make it into the tail.
+pub async fn supervise(
+    mut child: Child,
+    control: &mut mpsc::UnboundedReceiver<SessionControl>,
+    output_done: JoinHandle<()>,
+    tail: StderrTail,
+) -> ExitInfo {
+    let mut stop_reply = None;
+    let mut forced = false;
+
+    let status = tokio::select! {
+        status = child.wait() => status,
+        Some(SessionControl::Stop { grace, reply }) = control.recv() => {
+            stop_reply = Some(reply);
+            if let Some(pid) = child.id() {
+                terminate_gracefully(pid);
+            }
+            match timeout(grace, child.wait()).await {
+                Ok(status) => status,
+                Err(_) => {
+                    forced = true;
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            }
+        }
+    };
+
+    let _ = timeout(Duration::from_secs(1), output_done).await;
+
+    let mut exit = match status {
+        Ok(status) => ExitInfo::from_status(status, tail.snapshot()),
+        Err(e) => ExitInfo::from_error(e, tail.snapshot()),
+    };
+    exit.requested = stop_reply.is_some();
+    exit.forced = forced;
+
+    if let Some(reply) = stop_reply {
+        let _ = reply.send(exit.clone());
+    }
+    exit
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    fn spawn_with_tail(script: &str) -> (Child, JoinHandle<()>, StderrTail) {
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let tail = StderrTail::default();
+        let reader_tail = tail.clone();
+        let stderr = child.stderr.take().unwrap();
+        let reader = tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                reader_tail.push(&line);
+            }
+        });
+        (child, reader, tail)
+    }
+
+    #[tokio::test]
+    async fn test_supervise_reports_exit_code_and_stderr() {
+        let (child, reader, tail) = spawn_with_tail("echo 'ERROR: Device disconnected' >&2; exit 3");
+        let (_tx, mut rx) = mpsc::unbounded_channel();
+
+        let exit = supervise(child, &mut rx, reader, tail).await;
+        assert!(!exit.success);
+        assert!(!exit.requested);
+        assert_eq!(exit.code, Some(3));
+        assert_eq!(exit.stderr_tail, vec!["ERROR: Device disconnected".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_supervise_stop_request() {
+        let (child, reader, tail) = spawn_with_tail("exec sleep 30");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let supervisor = tokio::spawn(async move { supervise(child, &mut rx, reader, tail).await });
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(SessionControl::Stop { grace: Duration::from_secs(5), reply: reply_tx }).unwrap();
+
+        let exit = reply_rx.await.unwrap();
+        assert_eq!(exit.signal, Some(15));
+        assert!(exit.requested);
+        assert!(!exit.forced);
+        assert!(supervisor.await.unwrap().signal.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_supervise_stop_escalates_to_kill() {
+        let (child, reader, tail) = spawn_with_tail("trap '' TERM; exec sleep 30");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let supervisor = tokio::spawn(async move { supervise(child, &mut rx, reader, tail).await });
+        // Let the shell install its trap first
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(SessionControl::Stop { grace: Duration::from_millis(200), reply: reply_tx }).unwrap();
+
+        let exit = reply_rx.await.unwrap();
+        assert!(exit.forced);
+        assert_eq!(exit.signal, Some(9));
+        supervisor.await.unwrap();
+    }
+
+    #[test]
+    fn test_stderr_tail_is_bounded() {
+        let tail = StderrTail::default();
+        for i in 0..(STDERR_TAIL_LINES + 5) {
+            tail.push(&i.to_string());
+        }
+        let lines = tail.snapshot();
+        assert_eq!(lines.len(), STDERR_TAIL_LINES);
+        assert_eq!(lines[0], "5");
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tokio::sync::oneshot;
+use crate::adb::{remote_join, AdbClient, AdbError};
+
+/// Minimum time between two `transfer-progress` events of the same transfer.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferKind {
+    Push,
+    Pull,
+    Install,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferStatus {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of the `transfer-progress` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub kind: TransferKind,
+    /// File currently being transferred.
+    pub file: String,
+    pub bytes_done: u64,
+    /// Unknown for directory pulls.
+    pub bytes_total: Option<u64>,
+    /// Average bytes per second since the start.
+    pub rate: f64,
+    pub status: TransferStatus,
+    pub error: Option<String>,
+}
+
+/// Cancel handles of in-flight pushes, pulls and installs keyed by transfer id.
+/// Each registration gets a generation so a finished transfer can only remove
+/// its own handle, never a newer transfer that reused the id.
+#[derive(Default)]
+pub struct TransferRegistry {
+    handles: HashMap<String, (u64, oneshot::Sender<()>)>,
+    next_id: u64,
+}
+
+impl TransferRegistry {
+    /// Registers a transfer under `transfer_id`, or a fresh `transfer-N` id.
+    /// Returns the id, its generation and the receiver that fires on cancel.
+    pub fn register(&mut self, transfer_id: Option<String>) -> Result<(String, u64, oneshot::Receiver<()>), String> {
+        self.next_id += 1;
+        let generation = self.next_id;
+        let id = match transfer_id {
+            Some(id) if self.handles.contains_key(&id) => return Err(format!("Transfer id {} is already in use", id)),
+            Some(id) => id,
+            None => {
+                let mut n = generation;
+                while self.handles.contains_key(&format!("transfer-{}", n)) {
+                    n += 1;
+                }
+                format!("transfer-{}", n)
+            }
+        };
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.handles.insert(id.clone(), (generation, cancel_tx));
+        Ok((id, generation, cancel_rx))
+    }
+
+    /// Drops the handle of a finished transfer, if it's still the one registered.
+    pub fn release(&mut self, id: &str, generation: u64) {
+        if self.handles.get(id).is_some_and(|(g, _)| *g == generation) {
+            self.handles.remove(id);
+        }
+    }
+
+    /// Signals a transfer to stop, returning whether one was running under `id`.
+    pub fn cancel(&mut self, id: &str) -> bool {
+        match self.handles.remove(id) {
+            Some((_, cancel)) => {
+                let _ = cancel.send(());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Accumulates byte counts of one transfer and throttles the resulting events.
+pub struct ProgressTracker {
+    transfer_id: String,
+    kind: TransferKind,
+    file: String,
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressTracker {
+    pub fn new(transfer_id: &str, kind: TransferKind, file: &str, total: Option<u64>) -> Self {
+        Self {
+            transfer_id: transfer_id.to_string(),
+            kind,
+            file: file.to_string(),
+            total,
+            done: 0,
+            started: Instant::now(),
+            last_emit: None,
+        }
+    }
+
+    pub fn set_file(&mut self, file: &str) {
+        self.file = file.to_string();
+    }
+
+    /// Adds `bytes` to the count, returning an event when one is due.
+    pub fn advance(&mut self, bytes: u64) -> Option<TransferProgress> {
+        self.done += bytes;
+        if self.last_emit.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return None;
+        }
+        self.last_emit = Some(Instant::now());
+        Some(self.snapshot(TransferStatus::Running, None))
+    }
+
+    pub fn snapshot(&self, status: TransferStatus, error: Option<String>) -> TransferProgress {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        TransferProgress {
+            transfer_id: self.transfer_id.clone(),
+            kind: self.kind,
+            file: self.file.clone(),
+            bytes_done: self.done,
+            bytes_total: self.total,
+            rate: if elapsed > 0.0 { self.done as f64 / elapsed } else { 0.0 },
+            status,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of a push or pull: what made it across and what didn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSummary {
+    pub files: u32,
+    pub bytes: u64,
+    pub failures: Vec<TransferFailure>,
+}
+
+impl TransferSummary {
+    pub fn record(&mut self, path: &str, result: Result<u64, AdbError>) {
+        match result {
+            Ok(bytes) => {
+                self.files += 1;
+                self.bytes += bytes;
+            }
+            Err(e) => self.fail(path, e.to_string()),
+        }
+    }
+
+    pub fn fail(&mut self, path: &str, error: impl Into<String>) {
+        self.failures.push(TransferFailure { path: path.to_string(), error: error.into() });
+    }
+}
+
+/// Last component of a remote path, ignoring a trailing `/`.
+pub fn remote_basename(path: &str) -> &str {
+    path.trim_end_matches('/').rsplit('/').next().unwrap_or(path)
+}
+
+/// Where `adb pull` would put `remote`: inside `local` when that's an existing
+/// directory, at `local` itself otherwise.
+pub fn pull_destination(remote: &str, local: &Path) -> PathBuf {
+    if local.is_dir() {
+        local.join(remote_basename(remote))
+    } else {
+        local.to_path_buf()
+    }
+}
+
+/// Recursively pulls `remote_dir` into `local_dir`. Only failing to list the
+/// top directory is an error, anything below is recorded in the summary.
+/// `progress` gets the remote file and the size of each chunk received.
+pub async fn pull_dir(
+    client: &AdbClient,
+    serial: &str,
+    remote_dir: &str,
+    local_dir: &Path,
+    progress: &mut (dyn FnMut(&str, u64) + Send),
+) -> Result<TransferSummary, AdbError> {
+    let mut summary = TransferSummary::default();
+    let mut pending = vec![(remote_dir.to_string(), local_dir.to_path_buf(), client.list_dir(serial, remote_dir).await?)];
+
+    while let Some((remote, local, entries)) = pending.pop() {
+        if let Err(e) = tokio::fs::create_dir_all(&local).await {
+            summary.fail(&remote, e.to_string());
+            continue;
+        }
+
+        for entry in entries {
+            let remote_path = remote_join(&remote, &entry.name);
+            let local_path = local.join(&entry.name);
+
+            if entry.stat.is_dir() {
+                match client.list_dir(serial, &remote_path).await {
+                    Ok(children) => pending.push((remote_path, local_path, children)),
+                    Err(e) => summary.fail(&remote_path, e.to_string()),
+                }
+            } else if entry.stat.is_file() {
+                let result = client.pull(serial, &remote_path, &local_path, &mut |n| progress(&remote_path, n)).await;
+                summary.record(&remote_path, result);
+            } else if entry.stat.is_symlink() {
+                summary.fail(&remote_path, "Skipped symlink");
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Reads the totals from `adb push`/`adb pull` output, e.g.
+/// `/sdcard/DCIM/: 12 files pulled, 0 skipped. 35.2 MB/s (123456789 bytes in 3.343s)`.
+pub fn parse_cli_summary(output: &str) -> Option<TransferSummary> {
+    let line = output.lines().rev().find(|l| l.contains(" pulled") || l.contains(" pushed"))?;
+
+    let files = line
+        .split(", ")
+        .find_map(|part| {
+            // "<path>: <count> file(s) pulled"
+            let mut words = part.split_whitespace().rev();
+            let verb = words.next()?;
+            words.next()?;
+            if verb == "pulled" || verb == "pushed" { words.next()?.parse().ok() } else { None }
+        })
+        .unwrap_or(0);
+    let bytes = line
+        .rfind('(')
+        .and_then(|start| line[start + 1..].split_whitespace().next())
+        .and_then(|b| b.parse().ok())
+        .unwrap_or(0);
+
+    Some(TransferSummary { files, bytes, failures: Vec::new() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_rejects_shared_ids() {
+        let mut registry = TransferRegistry::default();
+        let (id, first, mut first_cancel) = registry.register(Some("upload".to_string())).unwrap();
+        assert!(registry.register(Some("upload".to_string())).is_err());
+        // The rejected registration didn't touch the running transfer
+        assert!(first_cancel.try_recv().is_err());
+
+        // Once cancelled, the id can be reused without the old transfer's cleanup removing the new one
+        assert!(registry.cancel(&id));
+        assert!(first_cancel.try_recv().is_ok());
+        let (_, second, mut second_cancel) = registry.register(Some("upload".to_string())).unwrap();
+        registry.release("upload", first);
+        assert!(registry.cancel("upload"));
+        assert!(second_cancel.try_recv().is_ok());
+        registry.release("upload", second);
+        assert!(!registry.cancel("upload"));
+    }
+
+    #[test]
+    fn test_registry_generated_ids_skip_taken_ones() {
+        let mut registry = TransferRegistry::default();
+        let (taken, _, _rx) = registry.register(Some("transfer-2".to_string())).unwrap();
+        let (generated, _, _rx2) = registry.register(None).unwrap();
+        assert_ne!(generated, taken);
+        assert!(registry.register(Some(generated)).is_err());
+    }
+
+    #[test]
+    fn test_remote_paths() {
+        assert_eq!(remote_basename("/sdcard/DCIM/"), "DCIM");
+        assert_eq!(remote_basename("/sdcard/a.txt"), "a.txt");
+        assert_eq!(remote_join("/sdcard/", "a.txt"), "/sdcard/a.txt");
+
+        let existing = std::env::temp_dir();
+        assert_eq!(pull_destination("/sdcard/DCIM", &existing), existing.join("DCIM"));
+        let fresh = existing.join("scrcpy_gui_missing_pull_dir");
+        assert_eq!(pull_destination("/sdcard/DCIM", &fresh), fresh);
+    }
+
+    #[test]
+    fn test_progress_tracker_throttles() {
+        let mut tracker = ProgressTracker::new("transfer-1", TransferKind::Push, "video.mp4", Some(1000));
+
+        let first = tracker.advance(100).unwrap();
+        assert_eq!((first.bytes_done, first.bytes_total), (100, Some(1000)));
+        assert_eq!(first.status, TransferStatus::Running);
+        assert!(tracker.advance(400).is_none());
+
+        let last = tracker.snapshot(TransferStatus::Done, None);
+        assert_eq!(last.bytes_done, 500);
+        assert_eq!(last.transfer_id, "transfer-1");
+        assert!(last.rate >= 0.0);
+    }
+
+    #[test]
+    fn test_parse_cli_summary() {
+        let dir = parse_cli_summary("/sdcard/DCIM/: 12 files pulled, 0 skipped. 35.2 MB/s (123456789 bytes in 3.343s)\n").unwrap();
+        assert_eq!((dir.files, dir.bytes), (12, 123456789));
+
+        let file = parse_cli_summary("C:\\a.txt: 1 file pushed, 0 skipped. 0.0 MB/s (7 bytes in 0.001s)\r\n").unwrap();
+        assert_eq!((file.files, file.bytes), (1, 7));
+
+        assert!(parse_cli_summary("adb: error: failed to stat remote object").is_none());
+    }
+}
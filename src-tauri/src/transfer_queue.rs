@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use serde::Serialize;
+
+/// Parallel pushes per device unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 2;
+/// Attempts per item, including the first one.
+pub const MAX_ATTEMPTS: u32 = 3;
+/// Queue item ids double as transfer ids, so other transfers can't use this prefix.
+pub const QUEUE_ID_PREFIX: &str = "queue-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl ItemStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, ItemStatus::Done | ItemStatus::Failed | ItemStatus::Cancelled)
+    }
+}
+
+/// One file of a batch push, reported through `list_transfer_queue` and `transfer-queue` events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    /// Also the transfer id of its `transfer-progress` events.
+    pub id: String,
+    pub device: String,
+    pub local_path: String,
+    pub remote_dir: String,
+    pub status: ItemStatus,
+    pub attempts: u32,
+    pub bytes: Option<u64>,
+    pub remote_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Pending and finished batch pushes. Lives in the backend, so a reloaded
+/// frontend can pick the state back up with `list_transfer_queue`.
+pub struct TransferQueue {
+    items: Vec<QueueItem>,
+    limits: HashMap<String, usize>,
+    default_limit: usize,
+    next_id: u64,
+}
+
+impl Default for TransferQueue {
+    fn default() -> Self {
+        Self { items: Vec::new(), limits: HashMap::new(), default_limit: DEFAULT_CONCURRENCY, next_id: 1 }
+    }
+}
+
+/// Failures worth another attempt: the connection dropped rather than the device refusing the file.
+pub fn is_transient(error: &str) -> bool {
+    const TRANSIENT: [&str; 8] = [
+        "connection reset",
+        "broken pipe",
+        "timed out",
+        "unexpected eof",
+        "early eof",
+        "device offline",
+        "closed",
+        "failed to reach adb server",
+    ];
+    let error = error.to_lowercase();
+    TRANSIENT.iter().any(|t| error.contains(t))
+}
+
+pub fn is_queue_id(id: &str) -> bool {
+    id.starts_with(QUEUE_ID_PREFIX)
+}
+
+impl TransferQueue {
+    pub fn items(&self) -> Vec<QueueItem> {
+        self.items.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&QueueItem> {
+        self.items.iter().find(|i| i.id == id)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Option<&mut QueueItem> {
+        self.items.iter_mut().find(|i| i.id == id)
+    }
+
+    pub fn enqueue(&mut self, device: &str, local_paths: &[String], remote_dir: &str) -> Vec<QueueItem> {
+        let start = self.items.len();
+        for path in local_paths {
+            let id = format!("{}{}", QUEUE_ID_PREFIX, self.next_id);
+            self.next_id += 1;
+            self.items.push(QueueItem {
+                id,
+                device: device.to_string(),
+                local_path: path.clone(),
+                remote_dir: remote_dir.to_string(),
+                status: ItemStatus::Queued,
+                attempts: 0,
+                bytes: None,
+                remote_path: None,
+                error: None,
+            });
+        }
+        self.items[start..].to_vec()
+    }
+
+    /// Sets the concurrency of one device, or the default for all others.
+    pub fn set_limit(&mut self, device: Option<&str>, limit: usize) {
+        let limit = limit.max(1);
+        match device {
+            Some(device) => {
+                self.limits.insert(device.to_string(), limit);
+            }
+            None => self.default_limit = limit,
+        }
+    }
+
+    fn limit(&self, device: &str) -> usize {
+        self.limits.get(device).copied().unwrap_or(self.default_limit)
+    }
+
+    /// Marks as many queued items running as the per-device limits allow, in
+    /// queue order, and returns them.
+    pub fn start_ready(&mut self) -> Vec<QueueItem> {
+        let mut running: HashMap<String, usize> = HashMap::new();
+        for item in self.items.iter().filter(|i| i.status == ItemStatus::Running) {
+            *running.entry(item.device.clone()).or_default() += 1;
+        }
+
+        let mut started = Vec::new();
+        for i in 0..self.items.len() {
+            if self.items[i].status != ItemStatus::Queued {
+                continue;
+            }
+            let device = self.items[i].device.clone();
+            let count = running.entry(device.clone()).or_default();
+            if *count < self.limit(&device) {
+                *count += 1;
+                self.items[i].status = ItemStatus::Running;
+                started.push(self.items[i].clone());
+            }
+        }
+        started
+    }
+
+    pub fn record_attempt(&mut self, id: &str, error: Option<String>) -> Option<QueueItem> {
+        let item = self.get_mut(id)?;
+        item.attempts += 1;
+        item.error = error;
+        Some(item.clone())
+    }
+
+    pub fn finish(&mut self, id: &str, result: Result<(String, Option<u64>), String>, cancelled: bool) -> Option<QueueItem> {
+        let item = self.get_mut(id)?;
+        match result {
+            Ok((remote_path, bytes)) => {
+                item.status = ItemStatus::Done;
+                item.remote_path = Some(remote_path);
+                item.bytes = bytes;
+                item.error = None;
+            }
+            Err(e) => {
+                item.status = if cancelled { ItemStatus::Cancelled } else { ItemStatus::Failed };
+                item.error = Some(e);
+            }
+        }
+        Some(item.clone())
+    }
+
+    /// Cancels a queued item right away. Running ones are cancelled through
+    /// their transfer instead, so this returns `None` for them.
+    pub fn cancel_queued(&mut self, id: &str) -> Option<QueueItem> {
+        let item = self.get_mut(id).filter(|i| i.status == ItemStatus::Queued)?;
+        item.status = ItemStatus::Cancelled;
+        Some(item.clone())
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.items.retain(|i| !i.status.is_finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_start_ready_respects_device_limits() {
+        let mut queue = TransferQueue::default();
+        queue.enqueue("phone", &paths(&["a", "b", "c"]), "/sdcard/Download");
+        queue.enqueue("tablet", &paths(&["d", "e"]), "/sdcard/Download");
+        queue.set_limit(Some("tablet"), 1);
+
+        let started: Vec<String> = queue.start_ready().into_iter().map(|i| i.local_path).collect();
+        assert_eq!(started, vec!["a", "b", "d"]);
+        assert!(queue.start_ready().is_empty());
+
+        let first = queue.items()[0].id.clone();
+        queue.finish(&first, Ok(("/sdcard/Download/a".to_string(), Some(10))), false);
+        let started: Vec<String> = queue.start_ready().into_iter().map(|i| i.local_path).collect();
+        assert_eq!(started, vec!["c"]);
+    }
+
+    #[test]
+    fn test_cancel_and_clear() {
+        let mut queue = TransferQueue::default();
+        let items = queue.enqueue("phone", &paths(&["a", "b", "c"]), "/sdcard");
+        queue.set_limit(None, 1);
+        queue.start_ready();
+
+        assert!(queue.cancel_queued(&items[0].id).is_none());
+        assert_eq!(queue.cancel_queued(&items[1].id).unwrap().status, ItemStatus::Cancelled);
+
+        queue.record_attempt(&items[0].id, Some("Connection reset by peer".to_string()));
+        let failed = queue.finish(&items[0].id, Err("remote couldn't create file".to_string()), false).unwrap();
+        assert_eq!((failed.status, failed.attempts), (ItemStatus::Failed, 1));
+
+        queue.clear_finished();
+        assert_eq!(queue.items().len(), 1);
+        assert_eq!(queue.get(&items[2].id).unwrap().status, ItemStatus::Queued);
+    }
+
+    #[test]
+    fn test_queue_ids_are_reserved() {
+        let mut queue = TransferQueue::default();
+        let items = queue.enqueue("phone", &paths(&["a"]), "/sdcard");
+        assert!(is_queue_id(&items[0].id));
+        assert!(!is_queue_id("transfer-1"));
+        assert!(!is_queue_id("my-queue-1"));
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient("Connection reset by peer (os error 104)"));
+        assert!(is_transient("device offline"));
+        assert!(!is_transient("remote couldn't create file: Permission denied"));
+    }
+}
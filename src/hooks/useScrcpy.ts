@@ -28,20 +28,90 @@ export interface ScrcpyConfig {
     vdDpi?: number;
     rotation?: string;
     res?: string;
+    audioSource?: string;
+    audioCodec?: 'opus' | 'aac' | 'flac' | 'raw';
+    audioBitrate?: number;
+    audioBuffer?: number;
+    audioDup?: boolean;
+    audioPlayback?: boolean;
+    restartPolicy?: RestartPolicy;
     aspectRatioLock?: boolean;
 }
 
+export interface RestartPolicy {
+    maxRetries?: number;
+    backoffMs?: number;
+    maxBackoffMs?: number;
+    onlyOnAbnormalExit?: boolean;
+}
+
+export interface TransferProgress {
+    transferId: string;
+    kind: 'push' | 'pull' | 'install';
+    file: string;
+    bytesDone: number;
+    bytesTotal?: number;
+    rate: number;
+    status: 'running' | 'done' | 'failed' | 'cancelled';
+    error?: string;
+}
+
+export interface QueueItem {
+    id: string;
+    device: string;
+    localPath: string;
+    remoteDir: string;
+    status: 'queued' | 'running' | 'done' | 'failed' | 'cancelled';
+    attempts: number;
+    bytes?: number;
+    remotePath?: string;
+    error?: string;
+}
+
+export interface InstallOptions {
+    replace?: boolean;
+    downgrade?: boolean;
+    grantPermissions?: boolean;
+    testOnly?: boolean;
+    user?: string;
+    incremental?: boolean;
+}
+
+export interface PackageInfo {
+    package: string;
+    apkPath: string;
+    versionCode?: number;
+    versionName?: string;
+    system: boolean;
+    enabled: boolean;
+}
+
+export interface Device {
+    serial: string;
+    state: string;
+    model?: string;
+    product?: string;
+    device?: string;
+    transportId?: number;
+    transport: 'usb' | 'tcp' | 'emulator';
+    hint?: string;
+}
+
 export function useScrcpy() {
     const [devices, setDevices] = useState<string[]>([]);
     const [logs, setLogs] = useState<string[]>([]);
     const [activeDevice, setActiveDevice] = useState<string>("");
     const [status, setStatus] = useState<string>("");
+    const [transfers, setTransfers] = useState<Record<string, TransferProgress>>({});
+    const [transferQueue, setTransferQueue] = useState<QueueItem[]>([]);
     const [downloadProgress, setDownloadProgress] = useState<number>(0);
     const [isDownloading, setIsDownloading] = useState(false);
     const [scrcpyStatus, setScrcpyStatus] = useState<{ found: boolean, message: string }>({ found: false, message: "Checking..." });
     const [isAutoConnect, setIsAutoConnect] = useState<boolean>(true);
     const [isInitialized, setIsInitialized] = useState(false);
-    const [runningDevices, setRunningDevices] = useState<string[]>([]);
+    // Running session id -> device serial
+    const [runningSessions, setRunningSessions] = useState<Record<string, string>>({});
+    const runningDevices = [...new Set(Object.values(runningSessions))];
     const [defaultRecordPath, setDefaultRecordPath] = useState<string>("");
     const [detectedCameras, setDetectedCameras] = useState<{ id: string, name: string }[]>([]);
     const [isRefreshing, setIsRefreshing] = useState(false);
@@ -147,13 +217,15 @@ export function useScrcpy() {
 
         const unlistenStatus = listen<any>('scrcpy-status', (event) => {
             const data = event.payload;
-            if (data.device && typeof data.running === 'boolean') {
-                setRunningDevices(prev => {
+            if (data.sessionId && typeof data.running === 'boolean') {
+                setRunningSessions(prev => {
+                    const next = { ...prev };
                     if (data.running) {
-                        return [...new Set([...prev, data.device])];
+                        next[data.sessionId] = data.device;
                     } else {
-                        return prev.filter(d => d !== data.device);
+                        delete next[data.sessionId];
                     }
+                    return next;
                 });
             } else if (data.type === 'downloading') {
                 setIsDownloading(true);
@@ -168,9 +240,47 @@ export function useScrcpy() {
             }
         });
 
+        const unlistenTransfer = listen<TransferProgress>('transfer-progress', (event) => {
+            const progress = event.payload;
+            setTransfers(prev => {
+                const next = { ...prev };
+                if (progress.status === 'running') {
+                    next[progress.transferId] = progress;
+                } else {
+                    delete next[progress.transferId];
+                }
+                return next;
+            });
+        });
+
+        // The queue lives in the backend, so pick it back up after a reload
+        invoke<QueueItem[]>('list_transfer_queue').then(setTransferQueue).catch(console.error);
+        const unlistenQueue = listen<QueueItem>('transfer-queue', (event) => {
+            const item = event.payload;
+            setTransferQueue(prev => {
+                const index = prev.findIndex(i => i.id === item.id);
+                if (index === -1) return [...prev, item];
+                const next = [...prev];
+                next[index] = item;
+                return next;
+            });
+        });
+
+        const unlistenRestart = listen<any>('scrcpy-restart', (event) => {
+            const data = event.payload;
+            if (data.status === 'waiting') {
+                setStatus(`Reconnecting ${data.device} (${data.attempt}/${data.maxRetries})...`);
+            } else if (data.status === 'gave-up') {
+                setStatus(`Gave up reconnecting ${data.device}`);
+            }
+        });
+
         return () => {
             unlistenLog.then(f => f());
             unlistenStatus.then(f => f());
+            unlistenRestart.then(f => f());
+            unlistenTransfer.then(f => f());
+            unlistenQueue.then(f => f());
         };
     }, []);
 
@@ -209,9 +319,17 @@ export function useScrcpy() {
             const res: any = await invoke('get_devices', { customPath: customPath || config.scrcpyPath });
 
             if (!res.error) {
-                const newDevices = res.devices as string[];
+                const allDevices = res.devices as Device[];
+                const newDevices = allDevices.filter(d => d.state === 'device').map(d => d.serial);
                 const prevDevices = prevDevicesRef.current;
 
+                // Surface devices that adb sees but can't use yet (e.g. pending RSA prompt)
+                allDevices.filter(d => d.state !== 'device' && d.hint).forEach(d => {
+                    if (!silent) {
+                        setLogs(prev => [...prev.slice(-100), `[SYSTEM] ${d.serial} is ${d.state}: ${d.hint}`]);
+                    }
+                });
+
                 // Identify connections/disconnections
                 const added = newDevices.filter(d => !prevDevices.includes(d));
                 const removed = prevDevices.filter(d => !newDevices.includes(d));
@@ -248,7 +366,7 @@ export function useScrcpy() {
     const runScrcpy = async (config: ScrcpyConfig) => {
         try {
             setLogs(prev => [...prev.slice(-100), `[SYSTEM] Initializing scrcpy session for ${config.device}...`]);
-            await invoke('run_scrcpy', { config });
+            return await invoke<string>('run_scrcpy', { config });
         } catch (e: any) {
             setLogs(prev => [...prev.slice(-100), `[ERROR] Failed to start scrcpy: ${e}`]);
         }
@@ -256,7 +374,28 @@ export function useScrcpy() {
 
     const stopScrcpy = async (device: string) => {
         try {
-            await invoke('stop_scrcpy', { device });
+            const sessions: { id: string, device: string }[] = await invoke('list_sessions');
+            for (const session of sessions.filter(s => s.device === device)) {
+                const report: any = await invoke('stop_scrcpy', { sessionId: session.id });
+                if (report.exit?.forced) {
+                    setLogs(prev => [...prev.slice(-100), `[SYSTEM] ${session.id} didn't stop in time and was killed`]);
+                }
+                if (report.recording) {
+                    const { path, size, finalized } = report.recording;
+                    setLogs(prev => [...prev.slice(-100), `[SYSTEM] Recording ${path} (${size} bytes)${finalized === false ? ' may be truncated' : ''}`]);
+                }
+            }
+        } catch (e) {
+            console.error(e);
+        }
+    };
+
+    const stopAllSessions = async () => {
+        try {
+            const report: any = await invoke('stop_all_sessions');
+            for (const failure of report.failed ?? []) {
+                setLogs(prev => [...prev.slice(-100), `[ERROR] Failed to stop ${failure.sessionId}: ${failure.error}`]);
+            }
         } catch (e) {
             console.error(e);
         }
@@ -414,11 +553,14 @@ export function useScrcpy() {
         }
     };
 
-    const installApk = async (device: string, filePath: string, customPath?: string) => {
+    const installApk = async (device: string, filePath: string, options?: InstallOptions, customPath?: string) => {
         try {
             setLogs(prev => [...prev.slice(-100), `[SYSTEM] Installing APK on ${device}: ${filePath}...`]);
-            const res: any = await invoke('install_apk', { device, filePath, customPath: customPath || config.scrcpyPath });
+            const res: any = await invoke('install_apk', { device, filePath, options, customPath: customPath || config.scrcpyPath });
             setLogs(prev => [...prev.slice(-100), `[ADB] ${res.message}`]);
+            if (res.error) {
+                setLogs(prev => [...prev.slice(-100), `[ADB] ${res.error.explanation}. ${res.error.fix}`]);
+            }
             return res;
         } catch (e: any) {
             setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
@@ -426,6 +568,80 @@ export function useScrcpy() {
         }
     };
 
+    const enqueuePush = async (device: string, localPaths: string[], remoteDir?: string) => {
+        try {
+            await invoke('enqueue_push', { device, localPaths, remoteDir, customPath: config.scrcpyPath });
+        } catch (e: any) {
+            setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
+        }
+    };
+
+    const syncDir = async (
+        device: string,
+        localDir: string,
+        remoteDir: string,
+        options: { compare?: 'size-mtime' | 'hash', deleteExtras?: boolean, dryRun?: boolean } = {},
+    ) => {
+        try {
+            const report: any = await invoke('sync_dir', { device, localDir, remoteDir, ...options, customPath: config.scrcpyPath });
+            if (!report.dryRun) {
+                const failures = report.summary?.failures?.length ?? 0;
+                setLogs(prev => [...prev.slice(-100), `[ADB] Synced ${localDir} to ${remoteDir}: ${report.plan.actions.length} actions, ${failures} failed`]);
+            }
+            return report;
+        } catch (e: any) {
+            setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
+            return null;
+        }
+    };
+
+    const listPackages = async (device: string, filter?: 'all' | 'user' | 'system' | 'disabled' | 'enabled'): Promise<PackageInfo[]> => {
+        try {
+            return await invoke('list_packages', { device, filter, customPath: config.scrcpyPath });
+        } catch (e: any) {
+            setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
+            return [];
+        }
+    };
+
+    // command is one of uninstall_package, clear_app_data, force_stop_app, set_package_enabled, launch_app
+    const packageAction = async (command: string, device: string, pkg: string, extra: Record<string, unknown> = {}) => {
+        try {
+            await invoke(command, { device, package: pkg, ...extra, customPath: config.scrcpyPath });
+            return { success: true };
+        } catch (e: any) {
+            setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
+            return { success: false, message: e };
+        }
+    };
+
+    const backupApk = async (device: string, pkg: string, localDir: string, bundle = false) => {
+        try {
+            const res: any = await invoke('backup_apk', { device, package: pkg, localDir, bundle, customPath: config.scrcpyPath });
+            setLogs(prev => [...prev.slice(-100), `[ADB] Saved ${pkg} to ${res.bundle ?? res.files.join(', ')}`]);
+            return res;
+        } catch (e: any) {
+            setLogs(prev => [...prev.slice(-100), `Error: ${e}`]);
+            return null;
+        }
+    };
+
+    const cancelQueueItem = async (itemId: string) => {
+        try {
+            await invoke('cancel_queue_item', { itemId });
+        } catch (e) {
+            console.error(e);
+        }
+    };
+
+    const cancelTransfer = async (transferId: string) => {
+        try {
+            await invoke('cancel_transfer', { transferId });
+        } catch (e) {
+            console.error(e);
+        }
+    };
+
     const runTerminalCommand = async (command: string, customPath?: string) => {
         try {
             // Check if user specifically typed scrcpy or adb to format log nicely
@@ -467,6 +683,16 @@ export function useScrcpy() {
         refreshDevices,
         runScrcpy,
         stopScrcpy,
+        stopAllSessions,
+        transfers,
+        cancelTransfer,
+        transferQueue,
+        enqueuePush,
+        syncDir,
+        listPackages,
+        packageAction,
+        backupApk,
+        cancelQueueItem,
         downloadScrcpy,
         activeDevice,
         setActiveDevice,